@@ -0,0 +1,445 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::Path;
+use std::str::FromStr;
+
+use kp_build::{
+    Adaptation, Algorithm, Combine, Config, Cooling, CrossoverKind, Encoding, MutationKind, Repair,
+    SelectionScheme, Topology,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Genetic and exact solvers for multi-resource knapsack problems"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Arguments of solve when no command is given
+    #[command(flatten)]
+    pub solve: SolveArgs,
+
+    #[command(flatten)]
+    pub overrides: Overrides,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the genetic algorithm on every configured profile (default)
+    Solve(SolveArgs),
+    /// Load the configured problems and report what was read
+    Validate,
+    /// Check the feasibility and value of solutions against the problem
+    Verify(VerifyArgs),
+    /// Write a random problem in the .dat format
+    Generate(GenerateArgs),
+    /// Run the genetic algorithm over several seeds per profile and summarize the results
+    Bench(BenchArgs),
+}
+
+/// Config file location and per-field overrides, accepted by every command.
+#[derive(Args, Debug, Default)]
+pub struct Overrides {
+    /// Configuration file, ./config.yaml when omitted
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+    /// Individuals per generation
+    #[arg(long, global = true)]
+    pub population_size: Option<usize>,
+    /// Number of generations to run, 0 for no limit
+    #[arg(long = "generations", global = true)]
+    pub generations_count: Option<u32>,
+    /// Print progress every N generations, 0 to disable
+    #[arg(long, global = true)]
+    pub frequency: Option<u32>,
+    /// Problem file in the .dat format
+    #[arg(long, global = true)]
+    pub path: Option<String>,
+    /// Reference value for the "performing" ratio
+    #[arg(long, global = true)]
+    pub known_best: Option<u32>,
+    /// Generations without a new champion before restarting the population
+    #[arg(long, global = true)]
+    pub stability_threshold: Option<u32>,
+    /// Chance of mutating an offspring, per thousand
+    #[arg(long, global = true)]
+    pub mutations_per_1k: Option<u32>,
+    /// State limit of the exact DP solver, over all its layers
+    #[arg(long, global = true)]
+    pub dp_max_states: Option<usize>,
+    /// DP time limit, in seconds
+    #[arg(long, global = true)]
+    pub dp_time_limit: Option<u64>,
+    /// Node limit of branch-and-bound
+    #[arg(long, global = true)]
+    pub bnb_node_limit: Option<u64>,
+    /// Branch-and-bound time limit, in seconds
+    #[arg(long, global = true)]
+    pub bnb_time_limit: Option<u64>,
+    /// Print shadow prices and reduced costs after solving
+    #[arg(long, global = true)]
+    pub sensitivity: bool,
+    /// Seed of the random number generator
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+    /// Solution file seeding the initial population, may be repeated
+    #[arg(long, global = true)]
+    pub warm_start: Vec<String>,
+    /// File the genetic algorithm state is saved to, suffixed with the
+    /// profile index when several profiles would share it
+    #[arg(long, global = true)]
+    pub checkpoint: Option<String>,
+    /// Generations between two checkpoints
+    #[arg(long, global = true)]
+    pub checkpoint_every: Option<u32>,
+    /// Time limit of each profile, DP and branch-and-bound included, in
+    /// seconds
+    #[arg(long, global = true)]
+    pub time_limit: Option<u64>,
+    /// Stop once the champion reaches this value
+    #[arg(long, global = true)]
+    pub target_fitness: Option<u32>,
+    /// Stop once the champion reaches the known best value
+    #[arg(long, global = true)]
+    pub stop_at_known_best: bool,
+    /// Stop once the champion reaches the proven upper bound
+    #[arg(long, global = true)]
+    pub stop_at_bound: bool,
+    /// Stop after N generations without a new champion
+    #[arg(long, global = true)]
+    pub max_stale_generations: Option<u32>,
+    /// Stop when any or all of the criteria are met: any, all
+    #[arg(long, global = true)]
+    pub termination: Option<Combine>,
+    /// Genotype of the genetic algorithm: binary, integer
+    #[arg(long, global = true)]
+    pub encoding: Option<Encoding>,
+    /// Threads producing the offspring, 0 for one per core, at most one per
+    /// 64 individuals
+    #[arg(long, global = true)]
+    pub threads: Option<usize>,
+    /// Islands receiving the migrants of an island: ring, full
+    #[arg(long, global = true)]
+    pub topology: Option<Topology>,
+    /// Generations between two migrations, 0 to never migrate
+    #[arg(long, global = true)]
+    pub migration_interval: Option<u32>,
+    /// Individuals each island sends to each of its neighbours
+    #[arg(long, global = true)]
+    pub migration_size: Option<usize>,
+    /// Parent selection: roulette, tournament, rank, sus, truncation
+    #[arg(long, global = true)]
+    pub selection: Option<SelectionScheme>,
+    /// Individuals competing in each tournament
+    #[arg(long, global = true)]
+    pub tournament_size: Option<usize>,
+    /// Best to average selection ratio of the rank selection, from 1 to 2
+    #[arg(long, global = true)]
+    pub rank_pressure: Option<f64>,
+    /// Part of the population kept by the truncation selection
+    #[arg(long, global = true)]
+    pub truncation_ratio: Option<f64>,
+    /// Crossover operator with its weight, as kind=weight, may be repeated:
+    /// one_point, two_point, k_point, uniform, arithmetic, product, density
+    #[arg(long, global = true, value_parser = parse_weight::<CrossoverKind>)]
+    pub crossover: Vec<(CrossoverKind, f64)>,
+    /// Cut points of the k-point crossover
+    #[arg(long, global = true)]
+    pub crossover_points: Option<usize>,
+    /// Adapt the crossover weights to the operators making better children
+    #[arg(long, global = true)]
+    pub adaptive_crossover: bool,
+    /// Mutation operator with its weight, as kind=weight, may be repeated:
+    /// up, shift, reset, bit_flip, swap, step
+    #[arg(long, global = true, value_parser = parse_weight::<MutationKind>)]
+    pub mutation: Vec<(MutationKind, f64)>,
+    /// How the mutation rate adapts: none, one_fifth, diversity
+    #[arg(long, global = true)]
+    pub mutation_adaptation: Option<Adaptation>,
+    /// Share of distinct individuals the diversity adaptation aims for
+    #[arg(long, global = true)]
+    pub diversity_target: Option<f64>,
+    /// How infeasible individuals are repaired: random, greedy
+    #[arg(long, global = true)]
+    pub repair: Option<Repair>,
+    /// Greedy individuals put in every new population
+    #[arg(long, global = true)]
+    pub greedy_seeds: Option<usize>,
+    /// Best offspring improved by local search each generation, 0 to disable
+    #[arg(long, global = true)]
+    pub local_search: Option<usize>,
+    /// Solver of the profiles: genetic, annealing
+    #[arg(long, global = true)]
+    pub algorithm: Option<Algorithm>,
+    /// Steps of simulated annealing
+    #[arg(long, global = true)]
+    pub annealing_steps: Option<u64>,
+    /// Starting temperature of simulated annealing, 0 for the mean product value
+    #[arg(long, global = true)]
+    pub initial_temperature: Option<f64>,
+    /// Cooling schedule of simulated annealing: geometric, linear, reheating
+    #[arg(long, global = true)]
+    pub cooling: Option<Cooling>,
+    /// Temperature factor per step of the geometric and reheating coolings
+    #[arg(long, global = true)]
+    pub cooling_rate: Option<f64>,
+    /// Steps without a new best before the reheating cooling reheats
+    #[arg(long, global = true)]
+    pub reheat_after: Option<u64>,
+}
+
+#[derive(Args, Debug, Default, PartialEq)]
+pub struct SolveArgs {
+    /// Run the profiles on one thread each, progress lines are then omitted
+    #[arg(long)]
+    pub parallel: bool,
+    /// Print the solutions in a machine-readable format instead of the report
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+    /// Carry on the run saved in a checkpoint file, instead of the config
+    /// file profiles. Flags still override its config, except for the
+    /// problem path and encoding which must stay those of the checkpoint
+    #[arg(long, conflicts_with = "parallel")]
+    pub resume: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+    Yaml,
+}
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Solution file in JSON or YAML, as written by `solve --output`, or a map
+    /// of product quantities. Its problem path is used unless --path is given
+    pub solution: String,
+}
+
+#[derive(Args, Debug)]
+pub struct GenerateArgs {
+    #[arg(long, default_value_t = 7)]
+    pub products: usize,
+    #[arg(long, default_value_t = 5)]
+    pub resources: usize,
+    #[arg(long, default_value_t = 500)]
+    pub capacity: i64,
+    #[arg(long, default_value_t = 3)]
+    pub max_requirement: u32,
+    #[arg(long, default_value_t = 20)]
+    pub min_value: u32,
+    #[arg(long, default_value_t = 50)]
+    pub max_value: u32,
+    /// Output file, stdout when omitted
+    #[arg(long)]
+    pub output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Number of runs, seeded from --seed upwards
+    #[arg(long, default_value_t = 10)]
+    pub runs: u64,
+}
+
+impl Overrides {
+    /// Loads every profile of the config file, then applies the flags given
+    /// on the command line to each. A missing ./config.yaml falls back to the
+    /// defaults, a missing --config file is an error.
+    pub fn load(&self) -> Result<Vec<Config>, String> {
+        let mut configs = match &self.config {
+            Some(path) => Config::load_all(path)?,
+            None if Path::new("./config.yaml").exists() => Config::load_all("./config.yaml")?,
+            None => vec![Config::default()],
+        };
+
+        configs.iter_mut().for_each(|c| self.apply(c));
+        distinct_checkpoints(&mut configs);
+        Ok(configs)
+    }
+
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(v) = self.population_size {
+            config.population_size = v;
+        }
+        if let Some(v) = self.generations_count {
+            config.generations_count = v;
+        }
+        if let Some(v) = self.frequency {
+            config.frequency = v;
+        }
+        if let Some(v) = &self.path {
+            config.path = v.to_string();
+        }
+        if let Some(v) = self.known_best {
+            config.known_best = v;
+        }
+        if let Some(v) = self.stability_threshold {
+            config.stability_threshold = v;
+        }
+        if let Some(v) = self.mutations_per_1k {
+            config.mutations_per_1k = v;
+        }
+        if let Some(v) = self.dp_max_states {
+            config.dp_max_states = v;
+        }
+        if let Some(v) = self.dp_time_limit {
+            config.dp_time_limit = v;
+        }
+        if let Some(v) = self.bnb_node_limit {
+            config.bnb_node_limit = v;
+        }
+        if let Some(v) = self.bnb_time_limit {
+            config.bnb_time_limit = v;
+        }
+        if self.sensitivity {
+            config.sensitivity = true;
+        }
+        if self.seed.is_some() {
+            config.seed = self.seed;
+        }
+        if !self.warm_start.is_empty() {
+            config.warm_start = self.warm_start.clone();
+        }
+        if self.checkpoint.is_some() {
+            config.checkpoint = self.checkpoint.clone();
+        }
+        if let Some(v) = self.checkpoint_every {
+            config.checkpoint_every = v;
+        }
+        if self.time_limit.is_some() {
+            config.time_limit = self.time_limit;
+        }
+        if self.target_fitness.is_some() {
+            config.target_fitness = self.target_fitness;
+        }
+        if self.stop_at_known_best {
+            config.stop_at_known_best = true;
+        }
+        if self.stop_at_bound {
+            config.stop_at_bound = true;
+        }
+        if self.max_stale_generations.is_some() {
+            config.max_stale_generations = self.max_stale_generations;
+        }
+        if let Some(v) = self.termination {
+            config.termination = v;
+        }
+        if let Some(v) = self.encoding {
+            config.encoding = v;
+        }
+        if let Some(v) = self.threads {
+            config.threads = v;
+        }
+        if let Some(v) = self.topology {
+            config.topology = v;
+        }
+        if let Some(v) = self.migration_interval {
+            config.migration_interval = v;
+        }
+        if let Some(v) = self.migration_size {
+            config.migration_size = v;
+        }
+        if let Some(v) = self.selection {
+            config.selection = v;
+        }
+        if let Some(v) = self.tournament_size {
+            config.tournament_size = v;
+        }
+        if let Some(v) = self.rank_pressure {
+            config.rank_pressure = v;
+        }
+        if let Some(v) = self.truncation_ratio {
+            config.truncation_ratio = v;
+        }
+        if !self.crossover.is_empty() {
+            config.crossover = self.crossover.iter().copied().collect();
+        }
+        if let Some(v) = self.crossover_points {
+            config.crossover_points = v;
+        }
+        if self.adaptive_crossover {
+            config.adaptive_crossover = true;
+        }
+        if !self.mutation.is_empty() {
+            config.mutation = self.mutation.iter().copied().collect();
+        }
+        if let Some(v) = self.mutation_adaptation {
+            config.mutation_adaptation = v;
+        }
+        if let Some(v) = self.diversity_target {
+            config.diversity_target = v;
+        }
+        if let Some(v) = self.repair {
+            config.repair = v;
+        }
+        if let Some(v) = self.greedy_seeds {
+            config.greedy_seeds = v;
+        }
+        if let Some(v) = self.local_search {
+            config.local_search = v;
+        }
+        if let Some(v) = self.algorithm {
+            config.algorithm = v;
+        }
+        if let Some(v) = self.annealing_steps {
+            config.annealing_steps = v;
+        }
+        if let Some(v) = self.initial_temperature {
+            config.initial_temperature = v;
+        }
+        if let Some(v) = self.cooling {
+            config.cooling = v;
+        }
+        if let Some(v) = self.cooling_rate {
+            config.cooling_rate = v;
+        }
+        if let Some(v) = self.reheat_after {
+            config.reheat_after = v;
+        }
+    }
+}
+
+/// Gives every profile sharing a checkpoint path its own file, `run.json`
+/// becoming `run-0.json`, `run-1.json`... so that they neither overwrite each
+/// other nor race on the temporary file.
+fn distinct_checkpoints(configs: &mut [Config]) {
+    let paths = configs
+        .iter()
+        .filter_map(|c| c.checkpoint.clone())
+        .collect::<Vec<_>>();
+
+    for (i, config) in configs.iter_mut().enumerate() {
+        let Some(path) = &config.checkpoint else {
+            continue;
+        };
+        if paths.iter().filter(|p| *p == path).count() > 1 {
+            let file = Path::new(path);
+            let stem = file
+                .file_stem()
+                .map_or(path.as_str(), |s| s.to_str().unwrap_or(path));
+            let name = match file.extension().and_then(|e| e.to_str()) {
+                Some(ext) => format!("{}-{}.{}", stem, i, ext),
+                None => format!("{}-{}", stem, i),
+            };
+            config.checkpoint = Some(file.with_file_name(name).to_string_lossy().into_owned());
+        }
+    }
+}
+
+/// `kind=weight`, or `kind` alone for a weight of 1.
+fn parse_weight<K: FromStr<Err = String>>(s: &str) -> Result<(K, f64), String> {
+    match s.split_once('=') {
+        Some((kind, weight)) => Ok((
+            kind.parse()?,
+            weight
+                .parse()
+                .map_err(|e| format!("invalid weight `{}`: {}", weight, e))?,
+        )),
+        None => Ok((s.parse()?, 1.0)),
+    }
+}
@@ -1,25 +1,213 @@
-use std::fs;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::solver::{
+    Adaptation, Algorithm, Combine, Cooling, CrossoverKind, Encoding, MutationKind, Repair,
+    SelectionScheme, Topology,
+};
 
-#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
 pub struct Config {
     pub population_size: usize,
+    /// 0 for no generation limit
     pub generations_count: u32,
     pub frequency: u32,
     pub path: String,
     pub known_best: u32,
     pub stability_threshold: u32,
     pub mutations_per_1k: u32,
+    pub dp_max_states: usize,
+    /// DP time limit, in seconds
+    pub dp_time_limit: u64,
+    pub bnb_node_limit: u64,
+    pub bnb_time_limit: u64,
+    pub sensitivity: bool,
+    pub seed: Option<u64>,
+    /// Solution files whose plans seed the initial population
+    pub warm_start: Vec<String>,
+    /// File the genetic algorithm state is saved to
+    pub checkpoint: Option<String>,
+    pub checkpoint_every: u32,
+    /// Wall-clock limit of a profile, DP and branch-and-bound included, in
+    /// seconds
+    pub time_limit: Option<u64>,
+    pub target_fitness: Option<u32>,
+    /// Stop once the champion reaches `known_best`
+    pub stop_at_known_best: bool,
+    /// Stop once the champion reaches the proven upper bound
+    pub stop_at_bound: bool,
+    /// Generations without a new champion before stopping
+    pub max_stale_generations: Option<u32>,
+    /// Whether any or all of the stop criteria end the run
+    pub termination: Combine,
+    /// How the genetic algorithm stores product quantities
+    pub encoding: Encoding,
+    /// Threads producing the offspring of the genetic algorithm, 0 for one
+    /// per core, at most one per 64 individuals
+    pub threads: usize,
+    /// Island variants of the profile, the island mode is used when any
+    pub islands: Vec<IslandConfig>,
+    pub topology: Topology,
+    /// Generations between two migrations, 0 to never migrate
+    pub migration_interval: u32,
+    /// Individuals each island sends to each of its neighbours
+    pub migration_size: usize,
+    /// How the parents of each generation are picked
+    pub selection: SelectionScheme,
+    pub tournament_size: usize,
+    /// Best to average selection ratio of the rank selection, from 1 to 2
+    pub rank_pressure: f64,
+    /// Part of the population kept by the truncation selection
+    pub truncation_ratio: f64,
+    /// Weight of each crossover operator, the encoding defaults when empty
+    pub crossover: BTreeMap<CrossoverKind, f64>,
+    /// Cut points of the k-point crossover
+    pub crossover_points: usize,
+    /// Move the crossover weights towards the operators making children
+    /// better than their parents
+    pub adaptive_crossover: bool,
+    /// Weight of each mutation operator, the encoding defaults when empty
+    pub mutation: BTreeMap<MutationKind, f64>,
+    /// How the mutation rate moves away from `mutations_per_1k`
+    pub mutation_adaptation: Adaptation,
+    /// Share of distinct individuals below which the diversity adaptation
+    /// raises the mutation rate
+    pub diversity_target: f64,
+    /// How infeasible individuals are repaired
+    pub repair: Repair,
+    /// Greedy individuals put in every new population, after the warm start
+    /// plans
+    pub greedy_seeds: usize,
+    /// Best offspring improved by local search each generation, 0 to
+    /// disable
+    pub local_search: usize,
+    /// Solver of the profile
+    pub algorithm: Algorithm,
+    /// Steps of simulated annealing
+    pub annealing_steps: u64,
+    /// Starting temperature of simulated annealing, 0 for the mean product
+    /// value
+    pub initial_temperature: f64,
+    pub cooling: Cooling,
+    /// Temperature factor per step of the geometric and reheating coolings
+    pub cooling_rate: f64,
+    /// Steps without a new best before the reheating cooling reheats
+    pub reheat_after: u64,
+}
+
+/// Settings of an island that differ from its profile.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct IslandConfig {
+    pub population_size: Option<usize>,
+    pub stability_threshold: Option<u32>,
+    pub mutations_per_1k: Option<u32>,
+    pub encoding: Option<Encoding>,
+    pub selection: Option<SelectionScheme>,
+}
+
+impl IslandConfig {
+    /// The profile with the settings of the island.
+    pub fn apply(&self, config: &Config) -> Config {
+        let mut c = config.clone();
+        c.islands = Vec::new();
+        if let Some(v) = self.population_size {
+            c.population_size = v;
+        }
+        if let Some(v) = self.stability_threshold {
+            c.stability_threshold = v;
+        }
+        if let Some(v) = self.mutations_per_1k {
+            c.mutations_per_1k = v;
+        }
+        if let Some(v) = self.encoding {
+            c.encoding = v;
+        }
+        if let Some(v) = self.selection {
+            c.selection = v;
+        }
+        c
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            population_size: 100,
+            generations_count: 5000,
+            frequency: 500,
+            path: String::new(),
+            known_best: 0,
+            stability_threshold: 100,
+            mutations_per_1k: 100,
+            dp_max_states: 2_000_000,
+            dp_time_limit: 10,
+            bnb_node_limit: 1_000_000,
+            bnb_time_limit: 10,
+            sensitivity: false,
+            seed: None,
+            warm_start: Vec::new(),
+            checkpoint: None,
+            checkpoint_every: 100,
+            time_limit: None,
+            target_fitness: None,
+            stop_at_known_best: false,
+            stop_at_bound: false,
+            max_stale_generations: None,
+            termination: Combine::Any,
+            encoding: Encoding::Binary,
+            threads: 1,
+            islands: Vec::new(),
+            topology: Topology::Ring,
+            migration_interval: 50,
+            migration_size: 2,
+            selection: SelectionScheme::Roulette,
+            tournament_size: 3,
+            rank_pressure: 1.5,
+            truncation_ratio: 0.5,
+            crossover: BTreeMap::new(),
+            crossover_points: 3,
+            adaptive_crossover: false,
+            mutation: BTreeMap::new(),
+            mutation_adaptation: Adaptation::None,
+            diversity_target: 0.5,
+            repair: Repair::Random,
+            greedy_seeds: 0,
+            local_search: 0,
+            algorithm: Algorithm::Genetic,
+            annealing_steps: 100_000,
+            initial_temperature: 0.0,
+            cooling: Cooling::Geometric,
+            cooling_rate: 0.9999,
+            reheat_after: 5_000,
+        }
+    }
 }
 
 impl Config {
     pub fn load_params() -> Result<Config, String> {
-        let yaml = fs::read_to_string("./config.yaml").expect("Unable to read the file");
+        Self::load("./config.yaml")
+    }
+
+    pub fn load(path: &str) -> Result<Config, String> {
+        Self::load_all(path)?
+            .first()
+            .cloned()
+            .ok_or_else(|| format!("No configuration in {}", path))
+    }
+
+    /// Every profile of the file, in order.
+    pub fn load_all(path: &str) -> Result<Vec<Config>, String> {
+        let yaml =
+            fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
 
         let deserialized_map = serde_yaml::from_str::<Vec<Config>>(&yaml);
 
         match deserialized_map {
-            Ok(cfg) => Ok(cfg[0].clone()),
+            Ok(cfg) if cfg.is_empty() => Err(format!("No configuration in {}", path)),
+            Ok(cfg) => Ok(cfg),
             Err(msg) => Err(msg.to_string()),
         }
     }
@@ -1,7 +1,9 @@
+#[allow(clippy::module_inception)]
 mod config;
 
-pub use config::Config;
+pub use config::{Config, IslandConfig};
 
+#[cfg(test)]
 mod tests {
     use super::*;
 
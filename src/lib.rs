@@ -0,0 +1,5 @@
+pub mod config;
+pub mod solver;
+
+pub use config::{Config, IslandConfig};
+pub use solver::*;
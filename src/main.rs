@@ -1,121 +1,615 @@
-use rand::Rng;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::{fs, process, thread, time::Duration, time::Instant};
 
-mod config;
-use config::*;
-mod solver;
-use solver::*;
+use kp_build::*;
+
+mod cli;
+use cli::{BenchArgs, Cli, Command, GenerateArgs, OutputFormat, SolveArgs, VerifyArgs};
 
 fn main() {
-    let mut rng = rand::thread_rng();
-    let config = Config::load_params().unwrap();
-    let knapsack = KnapSack::load_custom_kp(&config);
-    // println!("{}", knapsack);
+    let cli = Cli::parse();
+    // solve arguments are taken before any command, for solve as the default
+    if cli.command.is_some() && cli.solve != SolveArgs::default() {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--parallel, --output and --resume go after solve, or with no command at all",
+            )
+            .exit();
+    }
+    let interrupt = Arc::new(AtomicBool::new(false));
+    handle_interrupt(&interrupt);
 
-    let mut pop = new_population(&config, &knapsack);
+    let result = match cli.command.unwrap_or(Command::Solve(cli.solve)) {
+        Command::Solve(args) => match &args.resume {
+            Some(path) => Checkpoint::load(path).and_then(|checkpoint| {
+                let mut config = checkpoint.config.clone();
+                cli.overrides.apply(&mut config);
+                solve(vec![config], &args, Some(checkpoint), &interrupt)
+            }),
+            None => cli
+                .overrides
+                .load()
+                .and_then(|c| solve(c, &args, None, &interrupt)),
+        },
+        Command::Validate => cli.overrides.load().and_then(validate),
+        Command::Verify(args) => cli
+            .overrides
+            .load()
+            .and_then(|c| verify(c, &args, cli.overrides.path.is_some())),
+        Command::Generate(args) => generate(&args, cli.overrides.seed),
+        Command::Bench(args) => cli
+            .overrides
+            .load()
+            .and_then(|c| bench(c, &args, &interrupt)),
+    };
 
-    let mut champion = pop[0].clone();
-    let mut champion_generation = 0;
-    let mut latest_generation = 0;
+    if let Err(msg) = result {
+        eprintln!("Error: {}", msg);
+        process::exit(1);
+    }
+}
 
-    for gen in 1..=config.generations_count {
-        let mut new_pop = Vec::new();
+/// Raises the flag on the first Ctrl-C, so that the genetic algorithm stops
+/// and its champion is still reported, and exits on the second.
+fn handle_interrupt(interrupt: &Arc<AtomicBool>) {
+    let flag = Arc::clone(interrupt);
+    let result = ctrlc::set_handler(move || {
+        if flag.swap(true, Ordering::Relaxed) {
+            process::exit(130);
+        }
+        eprintln!("Interrupted, stopping the run (Ctrl-C again to abort)");
+    });
 
-        let total_fitness = pop
-            .iter()
-            .map(|p| p.fitness)
-            .reduce(|total, fitness| total + fitness)
-            .expect("Invalid total_fitness");
+    if let Err(e) = result {
+        eprintln!("Unable to handle Ctrl-C: {}", e);
+    }
+}
+
+/// The stop criteria of the solver of a profile, `bound` being the proven
+/// upper bound of its problem and `spent` the time already taken from its
+/// time limit.
+fn profile_budget(
+    config: &Config,
+    bound: Option<u32>,
+    spent: Duration,
+    interrupt: &Arc<AtomicBool>,
+) -> Budget {
+    let mut targets = Vec::new();
+    targets.extend(config.target_fitness);
+    if config.stop_at_known_best && config.known_best > 0 {
+        targets.push(config.known_best);
+    }
+    if config.stop_at_bound {
+        targets.extend(bound);
+    }
+
+    Budget {
+        iterations: match config.algorithm {
+            Algorithm::Genetic => Some(u64::from(config.generations_count)),
+            Algorithm::Annealing => Some(config.annealing_steps),
+        }
+        .filter(|i| *i > 0),
+        time_limit: config
+            .time_limit
+            .map(|t| Duration::from_secs(t).saturating_sub(spent)),
+        targets,
+        stale_iterations: config.max_stale_generations.map(u64::from),
+        combine: config.termination,
+        interrupt: Some(Arc::clone(interrupt)),
+    }
+}
+
+/// One profile solved by its solver.
+struct Run {
+    config: Config,
+    knapsack: KnapSack,
+    warnings: Vec<Diagnostic>,
+    reference: String,
+    champion: Solution,
+}
+
+fn solve(
+    mut configs: Vec<Config>,
+    args: &SolveArgs,
+    mut resume: Option<Checkpoint>,
+    interrupt: &Arc<AtomicBool>,
+) -> Result<(), String> {
+    let text = args.output == OutputFormat::Text;
+    let paths = configs.iter().map(|c| c.path.clone()).collect::<Vec<_>>();
+
+    if args.parallel || !text {
+        configs.iter_mut().for_each(|c| c.frequency = 0);
+    }
+
+    // one result per profile, a failing profile doesn't end the batch
+    let mut results = Vec::new();
+    if args.parallel {
+        results = thread::scope(|s| {
+            let handles = configs
+                .into_iter()
+                .map(|config| s.spawn(|| run_profile(config, None, interrupt)))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| {
+                    h.join()
+                        .unwrap_or_else(|_| Err(String::from("the profile thread panicked")))
+                })
+                .collect::<Vec<_>>()
+        });
+        for (i, run) in results.iter().enumerate() {
+            explain_result(i, &paths, run, text);
+        }
+    } else {
+        for (i, config) in configs.into_iter().enumerate() {
+            if interrupt.load(Ordering::Relaxed) {
+                break;
+            }
+            let run = run_profile(config, resume.take(), interrupt);
+            explain_result(i, &paths, &run, text);
+            results.push(run);
+        }
+    }
 
-        if gen % config.frequency == 0 {
+    let solutions = results
+        .iter()
+        .filter_map(|r| r.as_ref().ok())
+        .map(|r| &r.champion)
+        .collect::<Vec<_>>();
+    match args.output {
+        OutputFormat::Text => (),
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&solutions).map_err(|e| e.to_string())?
+        ),
+        OutputFormat::Yaml => print!(
+            "{}",
+            serde_yaml::to_string(&solutions).map_err(|e| e.to_string())?
+        ),
+        OutputFormat::Csv => {
+            println!("{}", Solution::csv_header());
+            for (i, solution) in solutions.iter().enumerate() {
+                solution.csv_rows(i).iter().for_each(|r| println!("{}", r));
+            }
+        }
+    }
+
+    if text && results.len() > 1 {
+        println!("\nSummary\n-------");
+        println!("#\tfitness\tgen\ttime\t% known best\tpath");
+        for (i, result) in results.iter().enumerate() {
+            let run = match result {
+                Ok(run) => run,
+                Err(msg) => {
+                    println!("{}\terror: {}\t\t\t\t\t{}", i, msg, paths[i]);
+                    continue;
+                }
+            };
+            let performance = match run.config.known_best {
+                0 => String::from("-"),
+                best => format!(
+                    "{:.2}%",
+                    100_f64 * run.champion.fitness as f64 / best as f64
+                ),
+            };
             println!(
-                "Gen #{}, fitness:{} (ranging {}..{}) - current champion: {}",
-                gen,
-                total_fitness,
-                pop[0].fitness,
-                pop[pop.len() - 1].fitness,
-                champion.fitness
+                "{}\t{}\t{}\t{:.2}s\t{}\t\t{}",
+                i,
+                run.champion.fitness,
+                run.champion.generation,
+                run.champion.runtime,
+                performance,
+                run.config.path
             );
         }
+    }
+
+    let failed = results
+        .iter()
+        .filter_map(|r| r.as_ref().err())
+        .collect::<Vec<_>>();
+    match failed[..] {
+        [] => Ok(()),
+        [msg] if paths.len() == 1 => Err(msg.to_string()),
+        _ => Err(format!(
+            "{} of {} profile(s) failed",
+            failed.len(),
+            paths.len()
+        )),
+    }
+}
+
+/// Reports the run of a profile, or why it failed when it is part of a
+/// batch. The error of a single profile is the error of the command.
+fn explain_result(index: usize, paths: &[String], run: &Result<Run, String>, text: bool) {
+    match run {
+        Ok(run) => explain_run(index, run, text),
+        Err(msg) if paths.len() > 1 => {
+            eprintln!("Profile #{} ({}) failed: {}", index, paths[index], msg)
+        }
+        Err(_) => (),
+    }
+}
+
+fn run_profile(
+    mut config: Config,
+    resume: Option<Checkpoint>,
+    interrupt: &Arc<AtomicBool>,
+) -> Result<Run, String> {
+    config.seed.get_or_insert_with(rand::random);
+
+    // the time limit covers the reference solvers too
+    let profile_start = Instant::now();
+    let (knapsack, warnings) = load_problem(&config)?;
+    let (reference, bound) = compute_reference(&mut config, &knapsack, profile_start, interrupt);
+    let baseline = Greedy.solve(&knapsack, &Budget::default())?;
+    let reference = format!("{}\nGreedy baseline: {}$", reference, baseline.fitness);
+    // println!("{}", knapsack);
 
-        pop = distribute(&pop);
-        // pop.iter().for_each(|i| print!("<{}>", i.fitness));
-        // println!("--");
+    let budget = profile_budget(&config, bound, profile_start.elapsed(), interrupt);
+    let start = Instant::now();
+    let mut champion = profile_solver(&config, resume)?.solve(&knapsack, &budget)?;
 
-        while new_pop.len() < config.population_size {
-            let pick1 = pick_individual(&mut rng, total_fitness, &pop);
-            let first = &pop[pick1];
-            let pick2 = pick_individual(&mut rng, total_fitness, &pop);
-            let other = &pop[pick2];
+    champion.runtime = start.elapsed().as_secs_f64();
+    champion.seed = config.seed;
+    champion.config = Some(config.clone());
+    knapsack.annotate(&mut champion);
 
-            new_pop.push(knapsack.cross_genes(first, other));
+    Ok(Run {
+        config,
+        knapsack,
+        warnings,
+        reference,
+        champion,
+    })
+}
+
+/// The solver of a profile: simulated annealing, or the genetic algorithm
+/// or its island model when it has islands, with the warm start plans
+/// loaded.
+fn profile_solver(config: &Config, resume: Option<Checkpoint>) -> Result<Box<dyn Solver>, String> {
+    if config.algorithm == Algorithm::Annealing {
+        if resume.is_some() || config.checkpoint.is_some() {
+            return Err(String::from(
+                "Checkpoints aren't supported by simulated annealing",
+            ));
         }
+        return Ok(Box::new(SimulatedAnnealing::new(config)));
+    }
 
-        new_pop.sort_by(|a, b| b.fitness.cmp(&a.fitness));
+    let mut warm_start = Vec::new();
+    for path in &config.warm_start {
+        for solution in Solution::load(path)? {
+            warm_start.push(solution.quantities);
+        }
+    }
+
+    if config.islands.is_empty() {
+        let mut ga = GeneticAlgorithm::new(config);
+        ga.warm_start = warm_start;
+        ga.resume = resume;
+        return Ok(Box::new(ga));
+    }
 
-        if new_pop[0].fitness > champion.fitness {
-            champion = new_pop[0].clone();
-            champion_generation = gen;
-            // println!("> new champion @gen #{}: {}$.", gen, champion.fitness);
-        } else {
-            if (gen - champion_generation > config.stability_threshold)
-                & (gen - latest_generation > config.stability_threshold)
-            {
-                // println!("> too stable, new population @gen #{}.", gen);
-                new_pop = new_population(&config, &knapsack);
-                latest_generation = gen;
+    if resume.is_some() || config.checkpoint.is_some() {
+        return Err(String::from("Checkpoints aren't supported in island mode"));
+    }
+    let mut model = IslandModel::new(config);
+    model.warm_start = warm_start;
+    Ok(Box::new(model))
+}
+
+/// Prints the report of a run, or only its warnings, on stderr, when the
+/// solutions are printed in a machine-readable format.
+fn explain_run(index: usize, run: &Run, text: bool) {
+    if !text {
+        run.warnings
+            .iter()
+            .for_each(|w| eprintln!("{}: {}", run.config.path, w));
+        return;
+    }
+
+    println!(
+        "\n=======\nProfile #{}: {} (seed {})",
+        index,
+        run.config.path,
+        run.config.seed.unwrap_or_default()
+    );
+    run.warnings.iter().for_each(|w| println!("{}", w));
+    println!("{}", run.reference);
+    run.knapsack.explain_solution(&run.champion, &run.config);
+
+    if run.config.sensitivity {
+        match Sensitivity::new(&run.knapsack) {
+            Ok(report) => println!("\n{}", report),
+            Err(msg) => println!("Sensitivity analysis failed: {}", msg),
+        }
+    }
+}
+
+/// Replaces `known_best` with the proven optimum when DP or branch-and-bound
+/// can find it within their limits, and describes the outcome. Also returns
+/// the best proven upper bound, the LP relaxation one when both fail. Both
+/// stop on Ctrl-C, and within what is left of the profile time limit
+/// counted from `start`.
+fn compute_reference(
+    config: &mut Config,
+    knapsack: &KnapSack,
+    start: Instant,
+    interrupt: &Arc<AtomicBool>,
+) -> (String, Option<u32>) {
+    let time_limit = |limit: u64| {
+        let left = config
+            .time_limit
+            .map(|t| Duration::from_secs(t).saturating_sub(start.elapsed()));
+        Some(Duration::from_secs(limit).min(left.unwrap_or(Duration::MAX)))
+    };
+
+    let budget = Budget {
+        time_limit: time_limit(config.dp_time_limit),
+        interrupt: Some(Arc::clone(interrupt)),
+        ..Default::default()
+    };
+    match DynamicProgramming::new(config.dp_max_states).solve(knapsack, &budget) {
+        Ok(optimum) => {
+            config.known_best = optimum.fitness;
+            (
+                format!("Exact optimum (DP): {}$", optimum.fitness),
+                Some(optimum.fitness),
+            )
+        }
+        Err(msg) => {
+            let skipped = format!("Exact solver skipped: {}", msg);
+
+            let mut bnb = BranchAndBound::default();
+            let budget = Budget {
+                iterations: Some(config.bnb_node_limit),
+                time_limit: time_limit(config.bnb_time_limit),
+                interrupt: Some(Arc::clone(interrupt)),
+                ..Default::default()
+            };
+            let report = match bnb.solve(knapsack, &budget) {
+                Ok(r) => r,
+                Err(msg) => {
+                    let bound = knapsack
+                        .relaxation()
+                        .ok()
+                        .map(|lp| (lp.value + 1e-6).floor() as u32);
+                    return (
+                        format!("{}\nBranch-and-bound failed: {}", skipped, msg),
+                        bound,
+                    );
+                }
+            };
+            if report.is_optimal() {
+                config.known_best = report.fitness;
             }
+            (
+                format!(
+                    "{}\nBranch-and-bound: {}$, bound {}$, gap {:.2}% ({} nodes)",
+                    skipped,
+                    report.fitness,
+                    report.bound.unwrap_or_default(),
+                    100_f64 * report.gap().unwrap_or_default(),
+                    bnb.nodes
+                ),
+                report.bound,
+            )
         }
-        new_pop.pop();
-        new_pop.insert(0, champion.clone());
+    }
+}
+
+/// Loads and checks the problem of a profile. Parse errors and semantic
+/// errors fail, the warnings are returned with the problem.
+fn load_problem(config: &Config) -> Result<(KnapSack, Vec<Diagnostic>), String> {
+    let knapsack = KnapSack::load_custom_kp(config).map_err(|errors| {
+        errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    })?;
 
-        pop = new_pop;
+    let diagnostics = knapsack.check();
+    if diagnostics.iter().any(|d| d.is_error()) {
+        return Err(diagnostics
+            .iter()
+            .map(|d| format!("{}: {}", config.path, d))
+            .collect::<Vec<_>>()
+            .join("\n"));
     }
 
-    knapsack.explain_solution(&champion, champion_generation, &config);
+    Ok((knapsack, diagnostics))
 }
 
-fn new_population(config: &Config, knapsack: &KnapSack) -> Vec<Individual> {
-    let mut pop = (0..config.population_size)
-        .map(|_| Individual::new(knapsack))
-        .collect::<Vec<_>>();
-    pop.sort_by(|a, b| b.fitness.cmp(&a.fitness));
-    pop
+fn validate(configs: Vec<Config>) -> Result<(), String> {
+    let mut invalid = 0;
+
+    for config in configs {
+        let knapsack = match KnapSack::load_custom_kp(&config) {
+            Ok(k) => k,
+            Err(errors) => {
+                errors.iter().for_each(|e| println!("{}", e));
+                println!();
+                invalid += 1;
+                continue;
+            }
+        };
+
+        let diagnostics = knapsack.check();
+        diagnostics
+            .iter()
+            .for_each(|d| println!("{}: {}", config.path, d));
+        if diagnostics.iter().any(|d| d.is_error()) {
+            println!();
+            invalid += 1;
+            continue;
+        }
+
+        println!("{}\n-------\n{}", config.path, knapsack);
+        match knapsack.relaxation() {
+            Ok(lp) => println!("LP relaxation bound {:.2}$\n", lp.value),
+            Err(msg) => println!("LP relaxation failed: {}\n", msg),
+        }
+    }
+
+    match invalid {
+        0 => Ok(()),
+        n => Err(format!("{} invalid problem file(s)", n)),
+    }
 }
 
-fn distribute(pop: &[Individual]) -> Vec<Individual> {
-    let mut output = Vec::new();
+/// Verifies every solution of the file, against the problem it was solved
+/// for unless `path_override` says --path was given.
+fn verify(configs: Vec<Config>, args: &VerifyArgs, path_override: bool) -> Result<(), String> {
+    let mut invalid = 0;
 
-    for (i, item) in pop.iter().enumerate() {
-        match i % 2 {
-            0 => output.push(item.clone()),
-            1 => output.insert(0, item.clone()),
-            _ => {
-                println!("weird usize {}", i);
-            }
+    for (i, solution) in Solution::load(&args.solution)?.iter().enumerate() {
+        let mut config = configs[0].clone();
+        if let Some(c) = solution.config.as_ref().filter(|_| !path_override) {
+            config.path = c.path.to_string();
+        }
+
+        let (knapsack, _) = load_problem(&config)?;
+        let verification = Verification::new(&knapsack, solution);
+
+        println!(
+            "Solution #{} against {}\n-------\n{}\n",
+            i, config.path, verification
+        );
+        if !verification.is_valid() {
+            invalid += 1;
         }
     }
 
-    output
+    match invalid {
+        0 => Ok(()),
+        n => Err(format!("{} invalid solution(s)", n)),
+    }
 }
 
-fn pick_individual(
-    rng: &mut rand::rngs::ThreadRng,
-    total_fitness: u32,
-    pop: &[Individual],
-) -> usize {
-    if total_fitness == 0 {
-        return 0_usize;
+fn generate(args: &GenerateArgs, seed: Option<u64>) -> Result<(), String> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed.unwrap_or_else(rand::random));
+    let generator = Generator {
+        products: args.products,
+        resources: args.resources,
+        capacity: args.capacity,
+        max_requirement: args.max_requirement,
+        min_value: args.min_value,
+        max_value: args.max_value,
+    };
+    let data = generator.generate(&mut rng)?;
+
+    match &args.output {
+        Some(path) => fs::write(path, data).map_err(|e| format!("Unable to write {}: {}", path, e)),
+        None => {
+            print!("{}", data);
+            Ok(())
+        }
     }
-    let elite_ratio = rng.gen_range(1..4);
-    let pick_rng = rng.gen_range(0..total_fitness / elite_ratio);
+}
 
-    let mut cur_fitness = pop[0].fitness;
-    let mut cur_pick = 0;
+fn bench(
+    configs: Vec<Config>,
+    args: &BenchArgs,
+    interrupt: &Arc<AtomicBool>,
+) -> Result<(), String> {
+    for config in configs {
+        if interrupt.load(Ordering::Relaxed) {
+            break;
+        }
+        bench_profile(config, args, interrupt)?;
+    }
 
-    while cur_fitness < pick_rng {
-        cur_pick += 1;
-        cur_fitness += pop[cur_pick].fitness;
+    Ok(())
+}
+
+fn bench_profile(
+    mut config: Config,
+    args: &BenchArgs,
+    interrupt: &Arc<AtomicBool>,
+) -> Result<(), String> {
+    let first_seed = config.seed.unwrap_or_else(rand::random);
+    let (knapsack, warnings) = load_problem(&config)?;
+    println!("{}\n-------", config.path);
+    warnings.iter().for_each(|w| println!("{}", w));
+    let (reference, bound) = compute_reference(&mut config, &knapsack, Instant::now(), interrupt);
+    println!("{}", reference);
+
+    // each run gets the whole time limit, the reference is computed once
+    let budget = profile_budget(&config, bound, Duration::ZERO, interrupt);
+    let mut results = Vec::new();
+
+    // generations or steps, the iterations of the solver
+    let unit = match config.algorithm {
+        Algorithm::Genetic => "gen",
+        Algorithm::Annealing => "step",
+    };
+    println!("\nseed\tfitness\t{}\ttime\t{}/s", unit, unit);
+    for seed in first_seed..first_seed + args.runs {
+        if budget.interrupted() {
+            break;
+        }
+        let run = Config {
+            seed: Some(seed),
+            frequency: 0,
+            ..config.clone()
+        };
+        let mut solver = profile_solver(&run, None)?;
+
+        let start = Instant::now();
+        let champion = solver.solve(&knapsack, &budget)?;
+        let elapsed = start.elapsed().as_secs_f64();
+
+        println!(
+            "{}\t{}\t{}\t{:.2}s\t{:.0}",
+            seed,
+            champion.fitness,
+            champion.generation,
+            elapsed,
+            champion.iterations as f64 / elapsed
+        );
+        results.push((champion, elapsed));
+    }
+
+    if results.is_empty() {
+        return Ok(());
+    }
+
+    let runs = results.len() as f64;
+    let fitness = results.iter().map(|(c, _)| c.fitness);
+    let mean = results.iter().map(|(c, _)| c.fitness as f64).sum::<f64>() / runs;
+    println!(
+        "\nfitness min {} / mean {:.2} / max {}, mean {} {:.1}, mean time {:.2}s, {:.0} {}/s",
+        fitness.clone().min().unwrap_or_default(),
+        mean,
+        fitness.max().unwrap_or_default(),
+        unit,
+        results
+            .iter()
+            .map(|(c, _)| c.generation as f64)
+            .sum::<f64>()
+            / runs,
+        results.iter().map(|(_, t)| t).sum::<f64>() / runs,
+        results
+            .iter()
+            .map(|(c, _)| c.iterations as f64)
+            .sum::<f64>()
+            / results.iter().map(|(_, t)| t).sum::<f64>(),
+        unit
+    );
+    if config.known_best > 0 {
+        println!(
+            "mean performance {:.2}%",
+            100_f64 * mean / config.known_best as f64
+        );
     }
 
-    cur_pick
+    Ok(())
 }
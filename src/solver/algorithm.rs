@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{KnapSack, Solution};
+
+/// How the termination criteria of a budget are combined.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Combine {
+    /// Stop as soon as one criterion is met
+    #[default]
+    Any,
+    /// Stop once every criterion is met
+    All,
+}
+
+impl FromStr for Combine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(Self::Any),
+            "all" => Ok(Self::All),
+            _ => Err(format!("Unknown combination '{}', expected any or all", s)),
+        }
+    }
+}
+
+/// Solver running the profiles.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    /// Genetic algorithm, or its island model when the profile has islands
+    #[default]
+    Genetic,
+    /// Simulated annealing
+    Annealing,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "genetic" => Ok(Self::Genetic),
+            "annealing" => Ok(Self::Annealing),
+            _ => Err(format!(
+                "Unknown algorithm '{}', expected genetic or annealing",
+                s
+            )),
+        }
+    }
+}
+
+/// Where a search stands, as seen by its budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    pub iterations: u64,
+    pub best: u32,
+    /// Iteration the best value was found at
+    pub last_improvement: u64,
+}
+
+/// How long a solver may search: iterations are generations for the genetic
+/// algorithm, steps for simulated annealing, states for dynamic programming
+/// and nodes for branch-and-bound. The criteria set are combined with
+/// `combine`; the interrupt flag stops the search whatever the others.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    pub iterations: Option<u64>,
+    pub time_limit: Option<Duration>,
+    /// Values to reach, e.g. the known best or a proven upper bound
+    pub targets: Vec<u32>,
+    /// Iterations without improvement of the best value
+    pub stale_iterations: Option<u64>,
+    pub combine: Combine,
+    pub interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl Budget {
+    pub fn exhausted(&self, progress: &Progress, start: Instant) -> bool {
+        if self.interrupted() {
+            return true;
+        }
+
+        let mut criteria = Vec::new();
+        if let Some(max) = self.iterations {
+            criteria.push(progress.iterations >= max);
+        }
+        if let Some(max) = self.time_limit {
+            criteria.push(start.elapsed() >= max);
+        }
+        criteria.extend(self.targets.iter().map(|t| progress.best >= *t));
+        if let Some(max) = self.stale_iterations {
+            criteria.push(
+                progress
+                    .iterations
+                    .saturating_sub(progress.last_improvement)
+                    >= max,
+            );
+        }
+
+        match self.combine {
+            Combine::Any => criteria.iter().any(|c| *c),
+            Combine::All => !criteria.is_empty() && criteria.iter().all(|c| *c),
+        }
+    }
+
+    pub fn interrupted(&self) -> bool {
+        self.interrupt
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+}
+
+pub trait Solver {
+    fn solve(&mut self, knapsack: &KnapSack, budget: &Budget) -> Result<Solution, String>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_criteria() {
+        let progress = Progress {
+            iterations: 50,
+            best: 100,
+            last_improvement: 10,
+        };
+        let mut budget = Budget {
+            iterations: Some(1000),
+            targets: vec![100],
+            stale_iterations: Some(40),
+            ..Default::default()
+        };
+
+        assert!(budget.exhausted(&progress, Instant::now()));
+        budget.combine = Combine::All;
+        assert!(!budget.exhausted(&progress, Instant::now()));
+        budget.iterations = Some(50);
+        assert!(budget.exhausted(&progress, Instant::now()));
+    }
+}
@@ -0,0 +1,244 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::config::Config;
+
+use super::{Budget, KnapSack, Progress, Solution, Solver};
+
+/// Share of the initial temperature a reheat goes back to
+const REHEAT_RATIO: f64 = 0.5;
+/// Progress lines printed over a run, when `frequency` isn't 0
+const REPORTS: u64 = 10;
+
+/// How the temperature falls along the steps.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Cooling {
+    /// Multiplied by `cooling_rate` at each step
+    #[default]
+    Geometric,
+    /// Down to 0 at the last step
+    Linear,
+    /// Geometric, back to half the initial temperature after `reheat_after`
+    /// steps without a new best
+    Reheating,
+}
+
+impl FromStr for Cooling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "geometric" => Ok(Self::Geometric),
+            "linear" => Ok(Self::Linear),
+            "reheating" => Ok(Self::Reheating),
+            _ => Err(format!(
+                "Unknown cooling '{}', expected geometric, linear or reheating",
+                s
+            )),
+        }
+    }
+}
+
+/// Single trajectory over product quantities, from the greedy plan. Each
+/// step tries a random feasible move, a unit added, removed or traded for a
+/// unit of another product, and takes it when it gains, or with probability
+/// `exp(delta / temperature)` when it loses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedAnnealing {
+    /// Steps of the linear cooling, also the default step budget
+    pub steps: u64,
+    /// 0 for the mean product value
+    pub initial_temperature: f64,
+    pub cooling: Cooling,
+    pub cooling_rate: f64,
+    pub reheat_after: u64,
+    pub seed: Option<u64>,
+    pub frequency: u32,
+}
+
+/// Quantities and remaining resources of the current plan.
+struct State {
+    quantities: Vec<u32>,
+    remains: Vec<i64>,
+    fitness: u32,
+}
+
+impl State {
+    /// Change in value of adding `add` and removing `remove`, `None` when
+    /// out of the bounds or the resources.
+    fn delta(&self, knapsack: &KnapSack, add: Option<usize>, remove: Option<usize>) -> Option<i64> {
+        if add.is_some_and(|p| self.quantities[p] >= knapsack.maxima[p])
+            || remove.is_some_and(|p| self.quantities[p] == 0)
+            || (add.is_some() && add == remove)
+        {
+            return None;
+        }
+
+        let added = add.map(|p| knapsack.product_constraints(p));
+        let removed = remove.map(|p| knapsack.product_constraints(p));
+        let fits = (0..self.remains.len()).all(|i| {
+            self.remains[i] - added.map_or(0, |c| i64::from(c[i]))
+                + removed.map_or(0, |c| i64::from(c[i]))
+                >= 0
+        });
+
+        fits.then(|| {
+            add.map_or(0, |p| i64::from(knapsack.values[p]))
+                - remove.map_or(0, |p| i64::from(knapsack.values[p]))
+        })
+    }
+
+    fn apply(&mut self, knapsack: &KnapSack, add: Option<usize>, remove: Option<usize>) {
+        for (p, sign) in [(add, 1), (remove, -1)] {
+            let Some(p) = p else { continue };
+            self.quantities[p] = self.quantities[p].saturating_add_signed(sign);
+            for (r, c) in self.remains.iter_mut().zip(knapsack.product_constraints(p)) {
+                *r -= i64::from(sign) * i64::from(*c);
+            }
+            let value = i64::from(knapsack.values[p]);
+            self.fitness = (i64::from(self.fitness) + i64::from(sign) * value) as u32;
+        }
+    }
+}
+
+impl SimulatedAnnealing {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            steps: config.annealing_steps,
+            initial_temperature: config.initial_temperature,
+            cooling: config.cooling,
+            cooling_rate: config.cooling_rate,
+            reheat_after: config.reheat_after,
+            seed: config.seed,
+            frequency: config.frequency,
+        }
+    }
+
+    fn temperature(&self, initial: f64, current: f64, step: u64) -> f64 {
+        match self.cooling {
+            Cooling::Geometric | Cooling::Reheating => current * self.cooling_rate,
+            Cooling::Linear => initial * (1.0 - step as f64 / self.steps.max(1) as f64),
+        }
+    }
+}
+
+impl Solver for SimulatedAnnealing {
+    fn solve(&mut self, knapsack: &KnapSack, budget: &Budget) -> Result<Solution, String> {
+        let start = Instant::now();
+        let mut rng = match self.seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => ChaCha8Rng::from_entropy(),
+        };
+        let n = knapsack.products.len();
+        if n == 0 {
+            return Err(String::from("no product to make"));
+        }
+
+        let greedy = knapsack.construct(0.0, &mut rng);
+        let mut state = State {
+            quantities: knapsack.quantities(&greedy),
+            remains: greedy.remains(knapsack).collect(),
+            fitness: greedy.fitness,
+        };
+        let mut best = state.quantities.clone();
+        let mut progress = Progress {
+            best: state.fitness,
+            ..Default::default()
+        };
+
+        let initial = match self.initial_temperature > 0.0 {
+            true => self.initial_temperature,
+            false => knapsack.values.iter().map(|v| f64::from(*v)).sum::<f64>() / n as f64,
+        };
+        let mut temperature = initial;
+        let mut last_reheat = 0;
+        let report_every = (self.steps / REPORTS).max(1);
+
+        while !budget.exhausted(&progress, start) {
+            let step = progress.iterations + 1;
+            let (add, remove) = match rng.gen_range(0..3) {
+                0 => (Some(rng.gen_range(0..n)), None),
+                1 => (None, Some(rng.gen_range(0..n))),
+                _ => (Some(rng.gen_range(0..n)), Some(rng.gen_range(0..n))),
+            };
+
+            if let Some(delta) = state.delta(knapsack, add, remove) {
+                if delta >= 0
+                    || (temperature > 0.0 && rng.gen::<f64>() < (delta as f64 / temperature).exp())
+                {
+                    state.apply(knapsack, add, remove);
+                }
+            }
+            if state.fitness > progress.best {
+                progress.best = state.fitness;
+                progress.last_improvement = step;
+                best.clone_from(&state.quantities);
+            }
+
+            temperature = self.temperature(initial, temperature, step);
+            if self.cooling == Cooling::Reheating
+                && step - progress.last_improvement.max(last_reheat) > self.reheat_after
+            {
+                temperature = initial * REHEAT_RATIO;
+                last_reheat = step;
+            }
+            progress.iterations = step;
+
+            if self.frequency > 0 && step.is_multiple_of(report_every) {
+                println!(
+                    "Step #{}, fitness:{} (temperature {:.2}) - current champion: {}",
+                    step, state.fitness, temperature, progress.best
+                );
+            }
+        }
+
+        let champion = knapsack.from_quantities(&best);
+        Ok(Solution {
+            quantities: knapsack.decode(&champion),
+            fitness: champion.fitness,
+            generation: u32::try_from(progress.last_improvement).unwrap_or(u32::MAX),
+            iterations: progress.iterations,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedules_keep_plans_feasible() {
+        for cooling in [Cooling::Geometric, Cooling::Linear, Cooling::Reheating] {
+            let config = Config {
+                path: String::from("./problem3-low.dat"),
+                seed: Some(3),
+                frequency: 0,
+                annealing_steps: 20_000,
+                cooling,
+                ..Default::default()
+            };
+            let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+            let budget = Budget {
+                iterations: Some(config.annealing_steps),
+                ..Default::default()
+            };
+
+            let first = SimulatedAnnealing::new(&config).solve(&knapsack, &budget);
+            let other = SimulatedAnnealing::new(&config).solve(&knapsack, &budget);
+            let plan = first.clone().unwrap();
+
+            assert_eq!(first, other);
+            assert_eq!(plan.iterations, config.annealing_steps);
+            assert!(knapsack.validate(&knapsack.encode(&plan.quantities)));
+            assert_eq!(
+                knapsack.get_fitness(&knapsack.encode(&plan.quantities)),
+                plan.fitness
+            );
+        }
+    }
+}
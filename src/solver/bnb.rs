@@ -0,0 +1,251 @@
+use std::time::Instant;
+
+use super::{Budget, KnapSack, Progress, Solution, Solver};
+
+const EPSILON: f64 = 1e-6;
+
+/// Nodes explored by the latest search are kept in `nodes`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BranchAndBound {
+    pub nodes: u64,
+}
+
+/// Bounds on product quantities in a subtree, and the LP bound of its parent.
+struct Node {
+    lower: Vec<u32>,
+    upper: Vec<u32>,
+    bound: u64,
+}
+
+impl Solver for BranchAndBound {
+    /// Depth-first search over integer product quantities, pruning every
+    /// subtree whose LP relaxation can't beat the incumbent.
+    fn solve(&mut self, knapsack: &KnapSack, budget: &Budget) -> Result<Solution, String> {
+        let start = Instant::now();
+        let ids = knapsack.products.keys().collect::<Vec<_>>();
+        let values = knapsack
+            .products
+            .values()
+            .map(|p| p.value)
+            .collect::<Vec<_>>();
+        let maxima = knapsack
+            .products
+            .values()
+            .map(|p| p.max)
+            .collect::<Vec<_>>();
+        let matrix = knapsack.requirement_matrix();
+        let model = knapsack.linear_program();
+        let capacity = knapsack
+            .resources
+            .values()
+            .map(|r| r.amount)
+            .collect::<Vec<_>>();
+
+        let mut best = vec![0_u32; ids.len()];
+        let mut best_value = 0_u64;
+        let mut nodes = 0_u64;
+        let mut improved = 0_u64;
+        let mut stack = vec![Node {
+            lower: vec![0; ids.len()],
+            upper: maxima.clone(),
+            bound: root_bound(knapsack),
+        }];
+
+        if capacity.iter().any(|c| *c < 0) {
+            stack.clear();
+        }
+
+        loop {
+            let progress = Progress {
+                iterations: nodes,
+                best: saturate(best_value),
+                last_improvement: improved,
+            };
+            if budget.exhausted(&progress, start) {
+                break;
+            }
+            let node = match stack.pop() {
+                Some(n) => n,
+                None => break,
+            };
+            nodes += 1;
+
+            if node.bound <= best_value {
+                continue;
+            }
+
+            let residual = matrix
+                .iter()
+                .zip(&capacity)
+                .map(|(row, c)| {
+                    c - row
+                        .iter()
+                        .zip(&node.lower)
+                        .map(|(a, l)| i64::from(*a) * i64::from(*l))
+                        .sum::<i64>()
+                })
+                .collect::<Vec<_>>();
+            if residual.iter().any(|r| *r < 0) {
+                continue;
+            }
+
+            let mut lp = model.clone();
+            lp.bounds = residual.iter().map(|r| *r as f64).collect();
+            lp.bounds.extend(
+                node.upper
+                    .iter()
+                    .zip(&node.lower)
+                    .map(|(u, l)| f64::from(u - l)),
+            );
+            let relaxed = match lp.maximize() {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+
+            let offset = values
+                .iter()
+                .zip(&node.lower)
+                .map(|(v, l)| u64::from(*v) * u64::from(*l))
+                .sum::<u64>();
+            let bound = offset + (relaxed.value + EPSILON).floor() as u64;
+            if bound <= best_value {
+                continue;
+            }
+
+            // rounding down stays feasible since requirements are non negative
+            let x = node
+                .lower
+                .iter()
+                .zip(&relaxed.primal)
+                .map(|(l, p)| f64::from(*l) + p)
+                .collect::<Vec<_>>();
+            let rounded = x
+                .iter()
+                .map(|v| (v + EPSILON).floor() as u32)
+                .collect::<Vec<_>>();
+            let rounded_value = values
+                .iter()
+                .zip(&rounded)
+                .map(|(v, q)| u64::from(*v) * u64::from(*q))
+                .sum::<u64>();
+            if rounded_value > best_value {
+                best_value = rounded_value;
+                improved = nodes;
+                best = rounded.clone();
+            }
+
+            let branch = (0..x.len())
+                .map(|j| (j, x[j] - x[j].floor()))
+                .filter(|(_, f)| *f > EPSILON && *f < 1.0 - EPSILON)
+                .max_by(|(_, a), (_, b)| a.min(1.0 - a).total_cmp(&b.min(1.0 - b)));
+
+            if let Some((j, _)) = branch {
+                let mut down = Node {
+                    lower: node.lower.clone(),
+                    upper: node.upper.clone(),
+                    bound,
+                };
+                down.upper[j] = rounded[j];
+                let mut up = Node {
+                    lower: node.lower,
+                    upper: node.upper,
+                    bound,
+                };
+                up.lower[j] = rounded[j] + 1;
+
+                stack.push(down);
+                stack.push(up);
+            }
+        }
+
+        let open_bound = stack.iter().map(|n| n.bound).max().unwrap_or(0);
+
+        self.nodes = nodes;
+
+        Ok(Solution {
+            quantities: ids
+                .iter()
+                .zip(&best)
+                .map(|(id, q)| (id.to_string(), *q))
+                .collect(),
+            fitness: saturate(best_value),
+            bound: Some(saturate(open_bound.max(best_value))),
+            iterations: nodes,
+            ..Default::default()
+        })
+    }
+}
+
+/// Bound of the root node: the LP relaxation value, or every product at its
+/// maximum when the relaxation fails.
+fn root_bound(knapsack: &KnapSack) -> u64 {
+    match knapsack.relaxation() {
+        Ok(lp) => (lp.value + EPSILON).floor() as u64,
+        Err(_) => knapsack
+            .products
+            .values()
+            .map(|p| u64::from(p.value) * u64::from(p.max))
+            .sum(),
+    }
+}
+
+/// Plan value as a fitness, `u32::MAX` for the values it can't hold.
+fn saturate(value: u64) -> u32 {
+    value.min(u64::from(u32::MAX)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::DynamicProgramming;
+    use std::time::Duration;
+
+    #[test]
+    fn bnb_matches_dp() {
+        let config = Config {
+            path: String::from("./problem3-low.dat"),
+            ..Default::default()
+        };
+        let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+
+        let budget = Budget {
+            iterations: Some(100_000),
+            time_limit: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let solution = BranchAndBound::default().solve(&knapsack, &budget).unwrap();
+        let optimum = DynamicProgramming::new(1_000_000)
+            .solve(&knapsack, &budget)
+            .unwrap();
+
+        assert!(solution.is_optimal());
+        assert_eq!(solution.fitness, optimum.fitness);
+    }
+
+    #[test]
+    fn bounds_values_past_u32() {
+        let data = "resource: r0: rose: 2000000\n\
+                    resource: r1: tulipe: 2000000\n\
+                    product: p0: 5000: r0=3\n\
+                    product: p1: 4000: r1=3\n";
+        let mut knapsack = KnapSack::parse(data, "test.dat").unwrap();
+        knapsack.compute_constraints();
+
+        let budget = Budget {
+            iterations: Some(0),
+            ..Default::default()
+        };
+        let solution = BranchAndBound::default().solve(&knapsack, &budget).unwrap();
+
+        assert_eq!(solution.fitness, 0);
+        assert_eq!(solution.bound, Some(u32::MAX));
+
+        let budget = Budget {
+            iterations: Some(100),
+            ..Default::default()
+        };
+        let solution = BranchAndBound::default().solve(&knapsack, &budget).unwrap();
+        assert!(solution.bound >= Some(solution.fitness));
+    }
+}
@@ -0,0 +1,69 @@
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::config::Config;
+
+use super::{Individual, KnapSack};
+
+/// Everything the genetic algorithm needs to carry on from `generation`
+/// exactly as if it had never stopped.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub config: Config,
+    pub generation: u32,
+    pub population: Vec<Individual>,
+    pub champion: Individual,
+    pub champion_generation: u32,
+    pub latest_generation: u32,
+    pub rng: ChaCha8Rng,
+    /// Current weights of the crossover operators
+    #[serde(default)]
+    pub crossover_weights: Vec<f64>,
+    /// Current mutation rate, per thousand offspring, the initial one when
+    /// unset
+    #[serde(default)]
+    pub mutation_rate: Option<f64>,
+}
+
+impl Checkpoint {
+    /// Writes to a temporary file first, so a run killed while saving keeps
+    /// its previous checkpoint.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let data = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        let tmp = format!("{}.tmp", path);
+
+        fs::write(&tmp, data).map_err(|e| format!("Unable to write {}: {}", tmp, e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+
+    /// Checks the saved individuals against the problem they are resumed on,
+    /// which may not be theirs once the path or encoding is overridden.
+    pub fn check(&self, knapsack: &KnapSack) -> Result<(), String> {
+        let empty = Individual::empty(knapsack);
+
+        for indiv in self.population.iter().chain([&self.champion]) {
+            let mut fresh = indiv.clone();
+            let fits = indiv.genotype.len() == empty.genotype.len()
+                && indiv.quantities.len() == empty.quantities.len()
+                && indiv.usage.len() == empty.usage.len();
+            if fits {
+                knapsack.evaluate(&mut fresh);
+            }
+            if !fits || fresh != *indiv {
+                return Err(String::from(
+                    "The checkpoint doesn't match the problem, resume it with its own path and encoding",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data =
+            fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+
+        serde_json::from_str(&data).map_err(|e| format!("Invalid checkpoint {}: {}", path, e))
+    }
+}
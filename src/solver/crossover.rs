@@ -0,0 +1,428 @@
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::config::Config;
+
+use super::selection::spin;
+use super::{Encoding, Individual, KnapSack};
+
+/// Smallest pick probability of an operator under adaptive selection
+const MIN_PROBABILITY: f64 = 0.05;
+/// Weight of the latest generation in the adaptive qualities
+const ADAPTATION_RATE: f64 = 0.3;
+
+/// Makes a child from two parents.
+pub trait Crossover: Debug + Send + Sync {
+    /// The child, evaluated but neither mutated nor repaired.
+    fn cross(
+        &self,
+        knapsack: &KnapSack,
+        first: &Individual,
+        other: &Individual,
+        rng: &mut dyn RngCore,
+    ) -> Individual;
+}
+
+/// Crossover operators available in `Config`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossoverKind {
+    OnePoint,
+    TwoPoint,
+    KPoint,
+    Uniform,
+    Arithmetic,
+    Product,
+    Density,
+}
+
+impl FromStr for CrossoverKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "one_point" => Ok(Self::OnePoint),
+            "two_point" => Ok(Self::TwoPoint),
+            "k_point" => Ok(Self::KPoint),
+            "uniform" => Ok(Self::Uniform),
+            "arithmetic" => Ok(Self::Arithmetic),
+            "product" => Ok(Self::Product),
+            "density" => Ok(Self::Density),
+            _ => Err(format!(
+                "Unknown crossover '{}', expected one_point, two_point, k_point, uniform, \
+                 arithmetic, product or density",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CrossoverKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::OnePoint => "one_point",
+            Self::TwoPoint => "two_point",
+            Self::KPoint => "k_point",
+            Self::Uniform => "uniform",
+            Self::Arithmetic => "arithmetic",
+            Self::Product => "product",
+            Self::Density => "density",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl CrossoverKind {
+    pub fn build(&self, config: &Config) -> Arc<dyn Crossover> {
+        match self {
+            Self::OnePoint => Arc::new(KPoint { points: 1 }),
+            Self::TwoPoint => Arc::new(TwoPoint),
+            Self::KPoint => Arc::new(KPoint {
+                points: config.crossover_points.max(1),
+            }),
+            Self::Uniform => Arc::new(Uniform),
+            Self::Arithmetic => Arc::new(Arithmetic),
+            Self::Product => Arc::new(ProductAware),
+            Self::Density => Arc::new(DensityGuided),
+        }
+    }
+}
+
+/// The crossover operators of a run with their weights. When adaptive, the
+/// weights follow the share of children beating both their parents.
+#[derive(Debug, Clone)]
+pub struct Crossovers {
+    pub kinds: Vec<CrossoverKind>,
+    operators: Vec<Arc<dyn Crossover>>,
+    /// Initial weights
+    pub weights: Vec<f64>,
+    pub adaptive: bool,
+}
+
+impl Crossovers {
+    /// Operators and weights of `crossover`, or the encoding defaults when
+    /// empty: one-point, two-point and uniform for the binary encoding,
+    /// arithmetic and uniform for the integer one.
+    pub fn new(config: &Config) -> Self {
+        let weights = match config.crossover.is_empty() {
+            false => config.crossover.clone(),
+            true => match config.encoding {
+                Encoding::Binary => BTreeMap::from([
+                    (CrossoverKind::OnePoint, 1.0),
+                    (CrossoverKind::TwoPoint, 1.0),
+                    (CrossoverKind::Uniform, 1.0),
+                ]),
+                Encoding::Integer => BTreeMap::from([
+                    (CrossoverKind::Arithmetic, 1.0),
+                    (CrossoverKind::Uniform, 1.0),
+                ]),
+            },
+        };
+
+        Self {
+            kinds: weights.keys().copied().collect(),
+            operators: weights.keys().map(|k| k.build(config)).collect(),
+            weights: weights.values().map(|w| w.max(0.0)).collect(),
+            adaptive: config.adaptive_crossover,
+        }
+    }
+
+    /// Index of an operator, drawn with probability proportional to its
+    /// weight.
+    pub fn pick(&self, weights: &[f64], rng: &mut dyn RngCore) -> usize {
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return rng.gen_range(0..weights.len());
+        }
+
+        spin(weights, rng)
+    }
+
+    pub fn cross(
+        &self,
+        operator: usize,
+        knapsack: &KnapSack,
+        first: &Individual,
+        other: &Individual,
+        rng: &mut dyn RngCore,
+    ) -> Individual {
+        self.operators[operator].cross(knapsack, first, other, rng)
+    }
+
+    /// Weights for the next generation given the `(uses, successes)` of each
+    /// operator in the last one, by probability matching: every operator
+    /// keeps a small chance to be picked.
+    pub fn adapt(&self, weights: &[f64], stats: &[(u32, u32)]) -> Vec<f64> {
+        if !self.adaptive {
+            return weights.to_vec();
+        }
+
+        let total = weights.iter().sum::<f64>();
+        let qualities = weights
+            .iter()
+            .zip(stats)
+            .map(|(w, (uses, successes))| {
+                let q = if total > 0.0 { w / total } else { 0.0 };
+                match uses {
+                    0 => q,
+                    _ => {
+                        (1.0 - ADAPTATION_RATE) * q
+                            + ADAPTATION_RATE * f64::from(*successes) / f64::from(*uses)
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let n = qualities.len() as f64;
+        let floor = MIN_PROBABILITY.min(1.0 / n);
+        let sum = qualities.iter().sum::<f64>();
+        qualities
+            .iter()
+            .map(|q| match sum > 0.0 {
+                true => floor + (1.0 - n * floor) * q / sum,
+                false => 1.0 / n,
+            })
+            .collect()
+    }
+}
+
+/// Child taking each position from the first parent where `from_first`.
+fn masked(
+    knapsack: &KnapSack,
+    first: &Individual,
+    other: &Individual,
+    mut from_first: impl FnMut(usize) -> bool,
+) -> Individual {
+    let mut child = Individual::empty(knapsack);
+
+    for i in 0..knapsack.positions() {
+        let parent = if from_first(i) { first } else { other };
+        match knapsack.encoding {
+            Encoding::Binary => child.genotype.set(i, parent.genotype.get(i)),
+            Encoding::Integer => child.quantities[i] = parent.quantities[i],
+        }
+    }
+
+    knapsack.evaluate(&mut child);
+    child
+}
+
+/// Parents alternate at `points` random cut points.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KPoint {
+    pub points: usize,
+}
+
+impl Crossover for KPoint {
+    fn cross(
+        &self,
+        knapsack: &KnapSack,
+        first: &Individual,
+        other: &Individual,
+        rng: &mut dyn RngCore,
+    ) -> Individual {
+        let n = knapsack.positions().max(1);
+        let mut cuts = (0..self.points)
+            .map(|_| rng.gen_range(0..n))
+            .collect::<Vec<_>>();
+        cuts.sort_unstable();
+
+        masked(knapsack, first, other, |i| {
+            cuts.iter().filter(|c| i >= **c).count() % 2 == 0
+        })
+    }
+}
+
+/// The first parent outside of two random cut points, the other one between.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TwoPoint;
+
+impl Crossover for TwoPoint {
+    fn cross(
+        &self,
+        knapsack: &KnapSack,
+        first: &Individual,
+        other: &Individual,
+        rng: &mut dyn RngCore,
+    ) -> Individual {
+        let n = knapsack.positions().max(1);
+        let k1 = rng.gen_range(0..n);
+        let k2 = rng.gen_range(0..n);
+
+        masked(knapsack, first, other, |i| {
+            ((i < k1) & (i < k2)) | ((i > k1) & (i > k2))
+        })
+    }
+}
+
+/// Each position from either parent.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Uniform;
+
+impl Crossover for Uniform {
+    fn cross(
+        &self,
+        knapsack: &KnapSack,
+        first: &Individual,
+        other: &Individual,
+        rng: &mut dyn RngCore,
+    ) -> Individual {
+        masked(knapsack, first, other, |_| rng.gen())
+    }
+}
+
+/// Weighted mean of the parents quantities, the same weight for every
+/// product.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Arithmetic;
+
+impl Crossover for Arithmetic {
+    fn cross(
+        &self,
+        knapsack: &KnapSack,
+        first: &Individual,
+        other: &Individual,
+        rng: &mut dyn RngCore,
+    ) -> Individual {
+        let weight = rng.gen::<f64>();
+        let quantities = knapsack
+            .quantities(first)
+            .iter()
+            .zip(knapsack.quantities(other))
+            .map(|(a, b)| (weight * f64::from(*a) + (1.0 - weight) * f64::from(b)).round() as u32)
+            .collect::<Vec<_>>();
+
+        knapsack.from_quantities(&quantities)
+    }
+}
+
+/// Whole product quantities from either parent, rather than single genes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProductAware;
+
+impl Crossover for ProductAware {
+    fn cross(
+        &self,
+        knapsack: &KnapSack,
+        first: &Individual,
+        other: &Individual,
+        rng: &mut dyn RngCore,
+    ) -> Individual {
+        let from_first = (0..knapsack.products.len())
+            .map(|_| rng.gen::<bool>())
+            .collect::<Vec<_>>();
+
+        match knapsack.encoding {
+            Encoding::Binary => masked(knapsack, first, other, |g| {
+                from_first[knapsack.genes[g].product]
+            }),
+            Encoding::Integer => masked(knapsack, first, other, |p| from_first[p]),
+        }
+    }
+}
+
+/// The larger of the parents quantities for the products of high value
+/// density, the smaller one for the others, the odds following the density
+/// rank. Repair then trims the plan.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DensityGuided;
+
+impl Crossover for DensityGuided {
+    fn cross(
+        &self,
+        knapsack: &KnapSack,
+        first: &Individual,
+        other: &Individual,
+        rng: &mut dyn RngCore,
+    ) -> Individual {
+        let ranks = knapsack.density_ranks();
+        let n = ranks.len().max(2) - 1;
+
+        let quantities = knapsack
+            .quantities(first)
+            .iter()
+            .zip(knapsack.quantities(other))
+            .zip(&ranks)
+            .map(|((a, b), rank)| {
+                // rank 0 is the densest product
+                match rng.gen_ratio((n - rank.min(&n)) as u32, n as u32) {
+                    true => b.max(*a),
+                    false => b.min(*a),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        knapsack.from_quantities(&quantities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{MutationKind, Mutations};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn offspring_are_valid() {
+        for encoding in [Encoding::Binary, Encoding::Integer] {
+            let config = Config {
+                path: String::from("./problem.dat"),
+                encoding,
+                mutations_per_1k: 500,
+                ..Default::default()
+            };
+            let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+            let mut rng = ChaCha8Rng::seed_from_u64(7);
+
+            for (kind, mutation) in [
+                CrossoverKind::OnePoint,
+                CrossoverKind::TwoPoint,
+                CrossoverKind::KPoint,
+                CrossoverKind::Uniform,
+                CrossoverKind::Arithmetic,
+                CrossoverKind::Product,
+                CrossoverKind::Density,
+            ]
+            .into_iter()
+            .flat_map(|k| {
+                [
+                    MutationKind::Up,
+                    MutationKind::Shift,
+                    MutationKind::Reset,
+                    MutationKind::BitFlip,
+                    MutationKind::Swap,
+                    MutationKind::Step,
+                ]
+                .map(|m| (k, m))
+            }) {
+                let operator = kind.build(&config);
+                let mutations = Mutations::new(&Config {
+                    mutation: BTreeMap::from([(mutation, 1.0)]),
+                    ..config.clone()
+                });
+                let mut first = Individual::new(&knapsack, &mut rng);
+                let other = Individual::new(&knapsack, &mut rng);
+
+                for _ in 0..50 {
+                    let mut child = operator.cross(&knapsack, &first, &other, &mut rng);
+                    mutations.mutate(mutations.rate, &knapsack, &mut child, &mut rng);
+                    first = knapsack.make_valid(child, &mut rng);
+                    let mut fresh = first.clone();
+                    knapsack.evaluate(&mut fresh);
+
+                    assert!(knapsack.validate(&first), "{:?} {:?}", kind, mutation);
+                    assert_eq!(first, fresh, "{:?} {:?}", kind, mutation);
+                    assert!(first
+                        .quantities
+                        .iter()
+                        .zip(&knapsack.maxima)
+                        .all(|(q, max)| q <= max));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use super::{Budget, KnapSack, LpSolution, Progress, Solution, Solver};
+
+/// Remaining resource amounts, in `KnapSack::resources` order.
+type State = Vec<i64>;
+
+/// States expanded between two checks of the budget
+const CHECK_EVERY: u64 = 1024;
+
+const EPSILON: f64 = 1e-6;
+
+/// Solves the problem exactly by dynamic programming over the remaining
+/// resource amounts, one product at a time.
+///
+/// Each layer maps a reachable state to the best value leading to it and the
+/// quantity of the last product used, which is enough to walk the optimal plan
+/// back. States whose value plus a relaxation bound on what the remaining
+/// products can add falls short of a good heuristic plan are dropped. Fails
+/// when the layers hold more than `max_states` states in all, or when the
+/// budget, in states expanded, runs out first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DynamicProgramming {
+    pub max_states: usize,
+}
+
+impl DynamicProgramming {
+    pub fn new(max_states: usize) -> Self {
+        Self { max_states }
+    }
+}
+
+impl Solver for DynamicProgramming {
+    fn solve(&mut self, knapsack: &KnapSack, budget: &Budget) -> Result<Solution, String> {
+        solve_dp(knapsack, self.max_states, budget)
+    }
+}
+
+/// Products and their requirements, in `KnapSack::products` order, and the
+/// single constraints the problem is relaxed to when bounding a state.
+struct Model {
+    values: Vec<u32>,
+    maxima: Vec<u32>,
+    requirements: Vec<State>,
+    /// Weights of the resources in each relaxed constraint: every resource
+    /// alone, then all of them weighted by their shadow prices
+    weights: Vec<Vec<f64>>,
+    /// Requirement of each product in each relaxed constraint
+    loads: Vec<Vec<f64>>,
+    /// Products loading each relaxed constraint, best value per unit first
+    orders: Vec<Vec<usize>>,
+}
+
+impl Model {
+    fn new(knapsack: &KnapSack, requirements: Vec<State>, lp: Option<&LpSolution>) -> Self {
+        let products = knapsack.products.values().collect::<Vec<_>>();
+        let resources = knapsack.resources.len();
+
+        let mut weights = (0..resources)
+            .map(|r| {
+                let mut w = vec![0.0; resources];
+                w[r] = 1.0;
+                w
+            })
+            .collect::<Vec<_>>();
+        // the LP relaxation bound at the root, tighter than any resource alone
+        weights.extend(lp.map(|lp| lp.duals[..resources].to_vec()));
+
+        let loads = weights
+            .iter()
+            .map(|w| {
+                requirements
+                    .iter()
+                    .map(|req| w.iter().zip(req).map(|(w, a)| w * *a as f64).sum())
+                    .collect::<Vec<f64>>()
+            })
+            .collect::<Vec<_>>();
+        let orders = loads
+            .iter()
+            .map(|load| {
+                let mut order = (0..products.len())
+                    .filter(|p| load[*p] > EPSILON)
+                    .collect::<Vec<_>>();
+                let density = |p: &usize| f64::from(products[*p].value) / load[*p];
+                order.sort_by(|a, b| density(b).total_cmp(&density(a)));
+                order
+            })
+            .collect();
+
+        Self {
+            values: products.iter().map(|p| p.value).collect(),
+            maxima: products.iter().map(|p| p.max).collect(),
+            requirements,
+            weights,
+            loads,
+            orders,
+        }
+    }
+
+    /// Upper bound on the value products `first..` can add within
+    /// `remaining`: the tightest of the fractional knapsacks over a single
+    /// relaxed constraint, each product capped by the quantity fitting all
+    /// the resources. `caps` is scratch space.
+    fn bound(&self, remaining: &[i64], first: usize, caps: &mut Vec<u32>) -> f64 {
+        caps.clear();
+        caps.extend((0..self.values.len()).map(|p| {
+            if p < first {
+                return 0;
+            }
+            remaining
+                .iter()
+                .zip(&self.requirements[p])
+                .filter(|(_, a)| **a > 0)
+                .map(|(r, a)| *r as f64 / *a as f64)
+                .fold(f64::from(self.maxima[p]), f64::min) as u32
+        }));
+        let total = self
+            .values
+            .iter()
+            .zip(caps.iter())
+            .map(|(v, c)| f64::from(*v) * f64::from(*c))
+            .sum::<f64>();
+
+        (0..self.weights.len())
+            .map(|k| {
+                let mut room = self.weights[k]
+                    .iter()
+                    .zip(remaining)
+                    .map(|(w, r)| w * *r as f64)
+                    .sum::<f64>();
+                let mut bound = total;
+                for &p in self.orders[k].iter().filter(|p| caps[**p] > 0) {
+                    let taken = f64::from(caps[p]).min(room.max(0.0) / self.loads[k][p]);
+                    bound -= f64::from(self.values[p]) * (f64::from(caps[p]) - taken);
+                    room -= taken * self.loads[k][p];
+                }
+                bound
+            })
+            .fold(total, f64::min)
+    }
+}
+
+fn solve_dp(knapsack: &KnapSack, max_states: usize, budget: &Budget) -> Result<Solution, String> {
+    let start = Instant::now();
+    let mut expanded = 0_u64;
+
+    if let Some(r) = knapsack.resources.values().find(|r| r.amount < 0) {
+        return Err(format!("resource {} has a negative amount", r.id));
+    }
+
+    let products = knapsack.products.values().collect::<Vec<_>>();
+    let requirements = products
+        .iter()
+        .map(|p| {
+            knapsack
+                .resources
+                .keys()
+                .map(|k| {
+                    p.requirements
+                        .iter()
+                        .filter(|q| &q.id == k)
+                        .map(|q| i64::from(q.amount))
+                        .sum()
+                })
+                .collect::<State>()
+        })
+        .collect::<Vec<_>>();
+    let lp = knapsack.relaxation().ok();
+    let model = Model::new(knapsack, requirements, lp.as_ref());
+    // a lower bound on the optimum, the states which can't reach it are dropped
+    let incumbent = f64::from(incumbent(knapsack, lp.as_ref()));
+
+    let capacity = knapsack
+        .resources
+        .values()
+        .map(|r| r.amount)
+        .collect::<State>();
+
+    let mut layers: Vec<HashMap<State, (u32, u32)>> = Vec::new();
+    let mut states = HashMap::from([(capacity, (0_u32, 0_u32))]);
+    let mut stored = states.len();
+    let mut caps = Vec::new();
+
+    for (i, product) in products.iter().enumerate() {
+        let mut next: HashMap<State, (u32, u32)> = HashMap::new();
+
+        for (remaining, (value, _)) in &states {
+            if expanded.is_multiple_of(CHECK_EVERY) {
+                let progress = Progress {
+                    iterations: expanded,
+                    last_improvement: expanded,
+                    ..Default::default()
+                };
+                if budget.exhausted(&progress, start) {
+                    return Err(format!("stopped after {} states", expanded));
+                }
+            }
+            expanded += 1;
+            let mut rem = remaining.clone();
+            for q in 0..=product.max {
+                let v = value.saturating_add(q.saturating_mul(product.value));
+                if f64::from(v) + model.bound(&rem, i, &mut caps) + EPSILON < incumbent {
+                    break;
+                }
+                match next.get_mut(&rem) {
+                    // ties go to the smallest quantity, so the walk back
+                    // doesn't depend on the hash map iteration order
+                    Some(e) => {
+                        if v > e.0 || (v == e.0 && q < e.1) {
+                            *e = (v, q);
+                        }
+                    }
+                    // a state kept once is kept with any better value
+                    None => {
+                        if f64::from(v) + model.bound(&rem, i + 1, &mut caps) + EPSILON >= incumbent
+                        {
+                            next.insert(rem.clone(), (v, q));
+                        }
+                    }
+                }
+
+                rem.iter_mut()
+                    .zip(&model.requirements[i])
+                    .for_each(|(r, a)| *r -= a);
+                if rem.iter().any(|r| *r < 0) {
+                    break;
+                }
+            }
+
+            if stored + next.len() > max_states {
+                return Err(format!(
+                    "more than {} states after product {}",
+                    max_states, product.id
+                ));
+            }
+        }
+
+        stored += next.len();
+        layers.push(states);
+        states = next;
+    }
+
+    let (remaining, &(fitness, mut quantity)) = states
+        .iter()
+        .max_by(|(ka, a), (kb, b)| a.0.cmp(&b.0).then(kb.cmp(ka)))
+        .expect("The DP has no final state");
+    let mut remaining = remaining.clone();
+
+    let mut solution = Solution {
+        fitness,
+        bound: Some(fitness),
+        ..Default::default()
+    };
+
+    for (i, product) in products.iter().enumerate().rev() {
+        solution.quantities.insert(product.id.to_string(), quantity);
+
+        remaining
+            .iter_mut()
+            .zip(&model.requirements[i])
+            .for_each(|(r, a)| *r += a * i64::from(quantity));
+        quantity = layers[i][&remaining].1;
+    }
+
+    Ok(solution)
+}
+
+/// Value of a good plan, the better of the greedy one and the LP relaxation
+/// rounded down then greedily refilled.
+fn incumbent(knapsack: &KnapSack, lp: Option<&LpSolution>) -> u32 {
+    let greedy = knapsack.construct(0.0, &mut ChaCha8Rng::seed_from_u64(0));
+    let rounded = lp.map_or(0, |lp| {
+        let quantities = lp.primal[..knapsack.products.len()]
+            .iter()
+            .map(|x| (x + EPSILON).floor() as u32)
+            .collect::<Vec<_>>();
+        knapsack
+            .greedy_repair(knapsack.from_quantities(&quantities))
+            .fitness
+    });
+
+    greedy.fitness.max(rounded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::time::Duration;
+
+    #[test]
+    fn dp_finds_known_optimum() {
+        for (path, optimum) in [("./problem3-low.dat", 1230), ("./problem.dat", 3507)] {
+            let config = Config {
+                path: String::from(path),
+                ..Default::default()
+            };
+            let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+
+            let solution = DynamicProgramming::new(1_000_000)
+                .solve(&knapsack, &Budget::default())
+                .unwrap();
+            let value = solution
+                .quantities
+                .iter()
+                .map(|(k, q)| knapsack.products[k].value * q)
+                .sum::<u32>();
+
+            assert_eq!(solution.fitness, optimum, "{}", path);
+            assert_eq!(value, optimum, "{}", path);
+            assert!(knapsack.validate(&knapsack.encode(&solution.quantities)));
+
+            let budget = Budget {
+                time_limit: Some(Duration::ZERO),
+                ..Default::default()
+            };
+            assert!(DynamicProgramming::new(1_000_000)
+                .solve(&knapsack, &budget)
+                .is_err());
+            assert!(DynamicProgramming::new(2)
+                .solve(&knapsack, &Budget::default())
+                .is_err());
+        }
+    }
+}
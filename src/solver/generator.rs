@@ -0,0 +1,60 @@
+use rand::Rng;
+
+const TITLES: [&str; 5] = ["rose", "tulipe", "marguerite", "lys", "lilas"];
+
+/// Parameters of a random problem, written in the .dat format.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Generator {
+    pub products: usize,
+    pub resources: usize,
+    pub capacity: i64,
+    pub max_requirement: u32,
+    pub min_value: u32,
+    pub max_value: u32,
+}
+
+impl Generator {
+    pub fn generate<R: Rng>(&self, rng: &mut R) -> Result<String, String> {
+        if self.resources == 0 || self.max_requirement == 0 {
+            return Err(String::from(
+                "at least one resource and a positive max requirement are needed",
+            ));
+        }
+        if self.min_value > self.max_value {
+            return Err(String::from("min value is above max value"));
+        }
+
+        let mut output = String::new();
+        for r in 0..self.resources {
+            let title = match TITLES.get(r) {
+                Some(t) => t.to_string(),
+                None => format!("resource{}", r),
+            };
+            output = format!("{}resource: r{}: {}: {}\n", output, r, title, self.capacity);
+        }
+        output += "\n";
+
+        for p in 0..self.products {
+            let mut requirements = (0..self.resources)
+                .map(|_| rng.gen_range(0..=self.max_requirement))
+                .collect::<Vec<_>>();
+            // a product needs something, or its max is unbounded
+            if requirements.iter().all(|r| *r == 0) {
+                requirements[rng.gen_range(0..self.resources)] = 1;
+            }
+
+            output = format!(
+                "{}product: p{}: {}",
+                output,
+                p,
+                rng.gen_range(self.min_value..=self.max_value)
+            );
+            for (r, amount) in requirements.iter().enumerate() {
+                output = format!("{}: r{}={}", output, r, amount);
+            }
+            output += "\n";
+        }
+
+        Ok(output)
+    }
+}
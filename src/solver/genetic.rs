@@ -0,0 +1,454 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::thread;
+use std::time::Instant;
+
+use crate::config::Config;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::{
+    Adaptation, Budget, Checkpoint, Crossovers, Individual, KnapSack, LocalSearch, Mutations,
+    Progress, Selection, Solution, Solver,
+};
+
+/// Fewest children worth a thread, spawning one costs about as much as
+/// breeding a few dozen children on the sample problems
+const MIN_CHILDREN_PER_THREAD: usize = 64;
+
+/// Spread of the density factors of the greedy seeds after the first one
+const GREEDY_NOISE: f64 = 0.3;
+
+#[derive(Debug, Clone)]
+pub struct GeneticAlgorithm {
+    pub population_size: usize,
+    pub frequency: u32,
+    pub stability_threshold: u32,
+    pub seed: Option<u64>,
+    /// Threads producing the offspring, 0 for one per core, fewer when the
+    /// population is too small to share
+    pub threads: usize,
+    pub selection: Arc<dyn Selection>,
+    pub crossovers: Crossovers,
+    pub mutations: Mutations,
+    /// Plans put in every new population, restarts included, before the
+    /// random individuals.
+    pub warm_start: Vec<BTreeMap<String, u32>>,
+    /// Greedy individuals put in every new population after the warm start
+    /// plans, the first one without noise.
+    pub greedy_seeds: usize,
+    /// Best offspring improved by local search each generation
+    pub local_search: usize,
+    /// File the run state is saved to, every `checkpoint_every` generations
+    /// and once done.
+    pub checkpoint: Option<String>,
+    pub checkpoint_every: u32,
+    /// State to carry on from, instead of a new population.
+    pub resume: Option<Checkpoint>,
+    /// Saved with the checkpoints.
+    pub config: Config,
+}
+
+impl GeneticAlgorithm {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            population_size: config.population_size,
+            frequency: config.frequency,
+            stability_threshold: config.stability_threshold,
+            seed: config.seed,
+            threads: config.threads,
+            selection: config.selection.build(config),
+            crossovers: Crossovers::new(config),
+            mutations: Mutations::new(config),
+            warm_start: Vec::new(),
+            greedy_seeds: config.greedy_seeds,
+            local_search: config.local_search,
+            checkpoint: config.checkpoint.clone(),
+            checkpoint_every: config.checkpoint_every,
+            resume: None,
+            config: config.clone(),
+        }
+    }
+
+    fn new_population<R: Rng>(&self, knapsack: &KnapSack, rng: &mut R) -> Vec<Individual> {
+        let mut pop = self
+            .warm_start
+            .iter()
+            .take(self.population_size)
+            .map(|q| knapsack.make_valid(knapsack.encode(q), rng))
+            .collect::<Vec<_>>();
+        for i in 0..self.greedy_seeds.min(self.population_size - pop.len()) {
+            let noise = if i == 0 { 0.0 } else { GREEDY_NOISE };
+            pop.push(knapsack.construct(noise, rng));
+        }
+        while pop.len() < self.population_size {
+            pop.push(Individual::new(knapsack, rng));
+        }
+        pop.sort_by_key(|i| std::cmp::Reverse(i.fitness));
+        pop
+    }
+
+    /// Next generation, unsorted, with how often each operator made a child
+    /// better than both its parents. With several threads, each one gets an
+    /// equal share of the offspring and its own RNG seeded from `rng`, so a
+    /// run only depends on the seed, the thread count and the population
+    /// size. Each thread gets at least `MIN_CHILDREN_PER_THREAD` children.
+    fn offspring<R: Rng>(
+        &self,
+        knapsack: &KnapSack,
+        pop: &[Individual],
+        weights: &[f64],
+        rate: f64,
+        rng: &mut R,
+    ) -> (Vec<Individual>, Stats) {
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+        .min(self.population_size / MIN_CHILDREN_PER_THREAD)
+        .max(1);
+        if threads == 1 {
+            return self.breed(knapsack, pop, self.population_size, weights, rate, rng);
+        }
+
+        let seeds = (0..threads).map(|_| rng.gen::<u64>()).collect::<Vec<_>>();
+        thread::scope(|s| {
+            let handles = seeds
+                .into_iter()
+                .enumerate()
+                .map(|(i, seed)| {
+                    let count = (self.population_size + threads - 1 - i) / threads;
+                    s.spawn(move || {
+                        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                        self.breed(knapsack, pop, count, weights, rate, &mut rng)
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut new_pop = Vec::with_capacity(self.population_size);
+            let mut stats = Stats::new(weights.len());
+            for h in handles {
+                let (children, s) = h.join().expect("An offspring thread panicked");
+                new_pop.extend(children);
+                stats.add(&s);
+            }
+            (new_pop, stats)
+        })
+    }
+
+    /// `count` children, each from two parents picked by the selection and
+    /// an operator drawn from `weights`, then mutated for `rate` per
+    /// thousand of them.
+    fn breed<R: Rng>(
+        &self,
+        knapsack: &KnapSack,
+        pop: &[Individual],
+        count: usize,
+        weights: &[f64],
+        rate: f64,
+        rng: &mut R,
+    ) -> (Vec<Individual>, Stats) {
+        let parents = self.selection.select(pop, 2 * count, rng);
+        let mut stats = Stats::new(weights.len());
+
+        let children = parents
+            .chunks(2)
+            .map(|pair| {
+                let (first, other) = (&pop[pair[0]], &pop[pair[1]]);
+                let operator = self.crossovers.pick(weights, rng);
+                let mut child = self.crossovers.cross(operator, knapsack, first, other, rng);
+                let mutated = self.mutations.mutate(rate, knapsack, &mut child, rng);
+                let child = knapsack.make_valid(child, rng);
+
+                let better = child.fitness > first.fitness.max(other.fitness);
+                stats.crossovers[operator].0 += 1;
+                stats.crossovers[operator].1 += u32::from(better);
+                if mutated {
+                    stats.mutations.0 += 1;
+                    stats.mutations.1 += u32::from(better);
+                }
+                child
+            })
+            .collect();
+
+        (children, stats)
+    }
+
+    /// Run state at generation 0, from the seed and the warm start plans.
+    pub fn start(&self, knapsack: &KnapSack) -> Checkpoint {
+        let mut rng = match self.seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => ChaCha8Rng::from_entropy(),
+        };
+        let population = self.new_population(knapsack, &mut rng);
+
+        Checkpoint {
+            config: self.config.clone(),
+            generation: 0,
+            champion: population[0].clone(),
+            population,
+            champion_generation: 0,
+            latest_generation: 0,
+            rng,
+            crossover_weights: self.crossovers.weights.clone(),
+            mutation_rate: Some(self.mutations.rate),
+        }
+    }
+
+    /// Runs one generation: offspring, champion, and a new population when
+    /// the champion hasn't changed for `stability_threshold` generations.
+    pub fn step(&self, knapsack: &KnapSack, state: &mut Checkpoint) {
+        let gen = state.generation + 1;
+        let rng = &mut state.rng;
+        let pop = &state.population;
+
+        let total_fitness = pop
+            .iter()
+            .map(|p| p.fitness)
+            .reduce(|total, fitness| total + fitness)
+            .expect("Invalid total_fitness");
+
+        if self.frequency > 0 && gen.is_multiple_of(self.frequency) {
+            println!(
+                "Gen #{}, fitness:{} (ranging {}..{}) - current champion: {}",
+                gen,
+                total_fitness,
+                pop[0].fitness,
+                pop[pop.len() - 1].fitness,
+                state.champion.fitness
+            );
+            if self.crossovers.adaptive {
+                let weights = self
+                    .crossovers
+                    .kinds
+                    .iter()
+                    .zip(&state.crossover_weights)
+                    .map(|(k, w)| format!("{} {:.2}", k, w))
+                    .collect::<Vec<_>>();
+                println!("  crossover weights: {}", weights.join(", "));
+            }
+            if self.mutations.adaptation != Adaptation::None {
+                let rate = state.mutation_rate.unwrap_or(self.mutations.rate);
+                println!("  mutation rate: {:.1}/1k", rate);
+            }
+        }
+
+        if state.crossover_weights.len() != self.crossovers.weights.len() {
+            state.crossover_weights = self.crossovers.weights.clone();
+        }
+        let rate = state.mutation_rate.unwrap_or(self.mutations.rate);
+        let (mut new_pop, stats) =
+            self.offspring(knapsack, pop, &state.crossover_weights, rate, rng);
+        state.crossover_weights = self
+            .crossovers
+            .adapt(&state.crossover_weights, &stats.crossovers);
+        let (mutated, successes) = stats.mutations;
+        state.mutation_rate = Some(self.mutations.adapt(rate, mutated, successes, &new_pop));
+        new_pop.sort_by_key(|i| std::cmp::Reverse(i.fitness));
+        if self.local_search > 0 {
+            for child in new_pop.iter_mut().take(self.local_search) {
+                *child = LocalSearch.improve(knapsack, child);
+            }
+            new_pop.sort_by_key(|i| std::cmp::Reverse(i.fitness));
+        }
+
+        if new_pop[0].fitness > state.champion.fitness {
+            state.champion = new_pop[0].clone();
+            state.champion_generation = gen;
+            // println!("> new champion @gen #{}: {}$.", gen, champion.fitness);
+        } else if (gen - state.champion_generation > self.stability_threshold)
+            & (gen - state.latest_generation > self.stability_threshold)
+        {
+            // println!("> too stable, new population @gen #{}.", gen);
+            new_pop = self.new_population(knapsack, rng);
+            state.latest_generation = gen;
+        }
+        new_pop.pop();
+        new_pop.insert(0, state.champion.clone());
+
+        state.population = new_pop;
+        state.generation = gen;
+    }
+}
+
+/// `(uses, successes)` of each crossover operator and of the mutations, a
+/// success being a child better than both its parents.
+struct Stats {
+    crossovers: Vec<(u32, u32)>,
+    mutations: (u32, u32),
+}
+
+impl Stats {
+    fn new(operators: usize) -> Self {
+        Self {
+            crossovers: vec![(0, 0); operators],
+            mutations: (0, 0),
+        }
+    }
+
+    fn add(&mut self, other: &Stats) {
+        for (total, (uses, successes)) in self.crossovers.iter_mut().zip(&other.crossovers) {
+            total.0 += uses;
+            total.1 += successes;
+        }
+        self.mutations.0 += other.mutations.0;
+        self.mutations.1 += other.mutations.1;
+    }
+}
+
+impl Solver for GeneticAlgorithm {
+    fn solve(&mut self, knapsack: &KnapSack, budget: &Budget) -> Result<Solution, String> {
+        let start = Instant::now();
+        let mut state = match self.resume.take() {
+            Some(checkpoint) => {
+                checkpoint.check(knapsack)?;
+                checkpoint
+            }
+            None => self.start(knapsack),
+        };
+
+        loop {
+            let progress = Progress {
+                iterations: u64::from(state.generation),
+                best: state.champion.fitness,
+                last_improvement: u64::from(state.champion_generation),
+            };
+            if budget.exhausted(&progress, start) {
+                break;
+            }
+
+            self.step(knapsack, &mut state);
+
+            if let Some(path) = &self.checkpoint {
+                if self.checkpoint_every > 0
+                    && state.generation.is_multiple_of(self.checkpoint_every)
+                {
+                    state.save(path)?;
+                }
+            }
+        }
+
+        if let Some(path) = &self.checkpoint {
+            state.save(path)?;
+        }
+
+        Ok(Solution {
+            quantities: knapsack.decode(&state.champion),
+            fitness: state.champion.fitness,
+            generation: state.champion_generation,
+            iterations: u64::from(state.generation),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Encoding;
+
+    #[test]
+    fn same_seed_same_champion() {
+        let config = Config {
+            population_size: 20,
+            frequency: 1000,
+            stability_threshold: 10,
+            path: String::from("./problem3-low.dat"),
+            mutations_per_1k: 100,
+            seed: Some(42),
+            ..Default::default()
+        };
+        let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+        let budget = Budget {
+            iterations: Some(50),
+            ..Default::default()
+        };
+
+        let first = GeneticAlgorithm::new(&config).solve(&knapsack, &budget);
+        let other = GeneticAlgorithm::new(&config).solve(&knapsack, &budget);
+
+        assert_eq!(first, other);
+    }
+
+    #[test]
+    fn same_seed_and_threads_same_champion() {
+        let config = Config {
+            population_size: 200,
+            path: String::from("./problem3-low.dat"),
+            seed: Some(42),
+            threads: 4,
+            ..Default::default()
+        };
+        let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+        let budget = Budget {
+            iterations: Some(50),
+            ..Default::default()
+        };
+
+        let first = GeneticAlgorithm::new(&config).solve(&knapsack, &budget);
+        let other = GeneticAlgorithm::new(&config).solve(&knapsack, &budget);
+
+        assert_eq!(first, other);
+    }
+
+    #[test]
+    fn resumed_run_matches_straight_run() {
+        let path =
+            std::env::temp_dir().join(format!("kp-build-resume-{}.json", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let config = Config {
+            population_size: 20,
+            frequency: 0,
+            path: String::from("./problem3-low.dat"),
+            seed: Some(5),
+            adaptive_crossover: true,
+            mutation_adaptation: Adaptation::OneFifth,
+            ..Default::default()
+        };
+        let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+        let budget = |iterations| Budget {
+            iterations: Some(iterations),
+            ..Default::default()
+        };
+
+        let ga = GeneticAlgorithm::new(&config);
+        let mut state = ga.start(&knapsack);
+        (0..10).for_each(|_| ga.step(&knapsack, &mut state));
+        assert_ne!(state.crossover_weights, ga.crossovers.weights);
+        assert_ne!(state.mutation_rate, Some(ga.mutations.rate));
+        state.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), state);
+
+        let straight = GeneticAlgorithm::new(&config).solve(&knapsack, &budget(40));
+        let mut first = GeneticAlgorithm::new(&config);
+        first.checkpoint = Some(path.clone());
+        first.checkpoint_every = 0;
+        first.solve(&knapsack, &budget(20)).unwrap();
+        let mut resumed = GeneticAlgorithm::new(&config);
+        resumed.resume = Some(Checkpoint::load(&path).unwrap());
+        let resumed = resumed.solve(&knapsack, &budget(40));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(straight, resumed);
+
+        for path in ["./problem3-low.dat", "./problem.dat"] {
+            let other = Config {
+                path: String::from(path),
+                encoding: Encoding::Integer,
+                ..config.clone()
+            };
+            let other = KnapSack::load_custom_kp(&other).unwrap();
+            let mut resumed = GeneticAlgorithm::new(&config);
+            resumed.resume = Some(state.clone());
+            assert!(resumed.solve(&other, &budget(40)).is_err(), "{}", path);
+        }
+        let other = KnapSack::load_custom_kp(&Config {
+            path: String::from("./problem.dat"),
+            ..config.clone()
+        })
+        .unwrap();
+        let mut resumed = GeneticAlgorithm::new(&config);
+        resumed.resume = Some(state);
+        assert!(resumed.solve(&other, &budget(40)).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use super::{Budget, KnapSack, Solution, Solver};
+
+/// How infeasible individuals are brought back within the resources.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Repair {
+    /// Random or focused genes switched off until feasible
+    #[default]
+    Random,
+    /// Worst value-density products cut first, then the slack refilled with
+    /// the best ones
+    Greedy,
+}
+
+impl FromStr for Repair {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Self::Random),
+            "greedy" => Ok(Self::Greedy),
+            _ => Err(format!("Unknown repair '{}', expected random or greedy", s)),
+        }
+    }
+}
+
+/// Fills the resources with the products of best value density, each as
+/// much as it fits. Fast, feasible, and a baseline for the other solvers.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Greedy;
+
+impl Solver for Greedy {
+    fn solve(&mut self, knapsack: &KnapSack, _budget: &Budget) -> Result<Solution, String> {
+        let indiv = knapsack.construct(0.0, &mut ChaCha8Rng::seed_from_u64(0));
+
+        Ok(Solution {
+            quantities: knapsack.decode(&indiv),
+            fitness: indiv.fitness,
+            iterations: 1,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::{Encoding, Individual};
+
+    #[test]
+    fn greedy_repair_is_feasible_and_full() {
+        for encoding in [Encoding::Binary, Encoding::Integer] {
+            let config = Config {
+                path: String::from("./problem.dat"),
+                encoding,
+                ..Default::default()
+            };
+            let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+            let greedy = Greedy.solve(&knapsack, &Budget::default()).unwrap();
+            let everything = knapsack.from_quantities(&knapsack.maxima);
+            let repaired = knapsack.greedy_repair(everything);
+            let mut fresh = repaired.clone();
+            knapsack.evaluate(&mut fresh);
+
+            assert!(greedy.fitness > 0);
+            assert!(knapsack.validate(&repaired));
+            assert_eq!(repaired, fresh);
+            assert_eq!(
+                knapsack.greedy_repair(repaired.clone()),
+                repaired,
+                "{:?}",
+                encoding
+            );
+            assert!(knapsack.validate(&knapsack.greedy_repair(Individual::empty(&knapsack))));
+
+            let quantities = knapsack.quantities(&repaired);
+            let remains = repaired.remains(&knapsack).collect::<Vec<_>>();
+            let addable = (0..quantities.len())
+                .filter(|p| quantities[*p] < knapsack.maxima[*p])
+                .find(|p| {
+                    remains
+                        .iter()
+                        .zip(knapsack.product_constraints(*p))
+                        .all(|(r, c)| *r >= i64::from(*c))
+                });
+            assert_eq!(addable, None, "{:?}", encoding);
+        }
+    }
+}
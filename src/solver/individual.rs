@@ -1,132 +1,268 @@
-use rand::prelude::*;
-use std::collections::BTreeMap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-use super::KnapSack;
+use super::{Bitset, KnapSack};
 
-#[derive(Debug, Clone, Default, PartialEq)]
+/// How product quantities are stored in individuals.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// One gene per power of two of each product quantity
+    #[default]
+    Binary,
+    /// One quantity per product
+    Integer,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(Self::Binary),
+            "integer" => Ok(Self::Integer),
+            _ => Err(format!(
+                "Unknown encoding '{}', expected binary or integer",
+                s
+            )),
+        }
+    }
+}
+
+/// Only one of `genotype` and `quantities` is filled, depending on the
+/// encoding of the problem. `usage` and `fitness` follow the mutations.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Individual {
-    pub genotype: BTreeMap<String, bool>,
+    /// One bit per gene of the problem
+    pub genotype: Bitset,
+    /// One quantity per product
+    pub quantities: Vec<u32>,
+    /// Amount of each resource used
+    pub usage: Vec<i64>,
     pub fitness: u32,
     // pub valid: bool,
 }
 
-fn rand_mutation_key(keys: Vec<&str>) -> String {
-    let pos = rand::thread_rng().gen_range(0..(keys.len()));
-    keys[pos].to_string()
+fn rand_mutation_key<R: Rng>(keys: Vec<usize>, rng: &mut R) -> usize {
+    let pos = rng.gen_range(0..(keys.len()));
+    keys[pos]
 }
 
 impl Individual {
-    fn _new(problem: &KnapSack) -> Self {
-        let genes: BTreeMap<String, bool> = problem
-            .fitness
-            .keys()
-            .map(|k| (k.to_string(), random()))
-            .collect();
+    /// Nothing made, in the encoding of the problem.
+    pub fn empty(problem: &KnapSack) -> Self {
+        let (genes, products) = match problem.encoding {
+            Encoding::Binary => (problem.genes.len(), 0),
+            Encoding::Integer => (0, problem.products.len()),
+        };
 
         Self {
-            genotype: genes,
+            genotype: Bitset::new(genes),
+            quantities: vec![0; products],
+            usage: vec![0; problem.resources.len()],
             fitness: 0,
         }
     }
 
-    pub fn new(problem: &KnapSack) -> Self {
-        let n = Self::_new(problem);
+    fn _new<R: Rng>(problem: &KnapSack, rng: &mut R) -> Self {
+        let mut indiv = Self::empty(problem);
+        for g in 0..problem.genes.len() {
+            indiv.genotype.set(g, rng.gen());
+        }
+
+        problem.evaluate(&mut indiv);
+        indiv
+    }
+
+    fn _new_quantities<R: Rng>(problem: &KnapSack, rng: &mut R) -> Self {
+        let mut indiv = Self::empty(problem);
+        for (p, max) in problem.maxima.iter().enumerate() {
+            indiv.quantities[p] = rng.gen_range(0..=*max);
+        }
+
+        problem.evaluate(&mut indiv);
+        indiv
+    }
+
+    pub fn new<R: Rng>(problem: &KnapSack, rng: &mut R) -> Self {
+        let n = match problem.encoding {
+            Encoding::Binary => Self::_new(problem, rng),
+            Encoding::Integer => Self::_new_quantities(problem, rng),
+        };
+
+        problem.make_valid(n, rng)
+    }
+
+    /// Switches a gene, keeping `usage` and `fitness` up to date.
+    pub fn set_gene(&mut self, problem: &KnapSack, gene: usize, value: bool) {
+        if self.genotype.get(gene) == value {
+            return;
+        }
+        self.genotype.set(gene, value);
+
+        let sign = if value { 1 } else { -1 };
+        for (u, c) in self.usage.iter_mut().zip(problem.gene_constraints(gene)) {
+            *u += sign * i64::from(*c);
+        }
+        match value {
+            true => self.fitness += problem.genes[gene].value,
+            false => self.fitness -= problem.genes[gene].value,
+        }
+    }
+
+    /// Changes a product quantity, keeping `usage` and `fitness` up to date.
+    pub fn set_quantity(&mut self, problem: &KnapSack, product: usize, quantity: u32) {
+        let delta = i64::from(quantity) - i64::from(self.quantities[product]);
+        self.quantities[product] = quantity;
 
-        problem.make_valid(n)
+        for (u, c) in self
+            .usage
+            .iter_mut()
+            .zip(problem.product_constraints(product))
+        {
+            *u += delta * i64::from(*c);
+        }
+        self.fitness =
+            (i64::from(self.fitness) + delta * i64::from(problem.values[product])) as u32;
     }
 
-    fn mutate(&mut self, key: String, value: bool) {
-        self.genotype.entry(key).and_modify(|v| *v = value);
+    /// Amount of each resource left, negative when overdrawn.
+    pub fn remains<'a>(&'a self, problem: &'a KnapSack) -> impl Iterator<Item = i64> + 'a {
+        problem.capacity.iter().zip(&self.usage).map(|(c, u)| c - u)
     }
 
-    pub fn mutate_up(&mut self, problem: &KnapSack) {
-        if random::<bool>() & random::<bool>() {
-            self.rand_mutate_up();
+    pub fn mutate_up<R: Rng>(&mut self, problem: &KnapSack, rng: &mut R) {
+        if problem.encoding == Encoding::Integer {
+            return self.increment_quantity(problem, rng);
+        }
+        if rng.gen::<bool>() & rng.gen::<bool>() {
+            self.rand_mutate_up(problem, rng);
         } else {
-            self.focused_mutate_up(problem);
+            self.focused_mutate_up(problem, rng);
         }
     }
 
-    pub fn mutate_down(&mut self, problem: &KnapSack) {
-        if random::<bool>() & random::<bool>() {
-            self.rand_mutate_down();
+    pub fn mutate_down<R: Rng>(&mut self, problem: &KnapSack, rng: &mut R) {
+        if problem.encoding == Encoding::Integer {
+            return self.decrement_quantity(problem, rng);
+        }
+        if rng.gen::<bool>() & rng.gen::<bool>() {
+            self.rand_mutate_down(problem, rng);
         } else {
-            self.focused_mutate_down(problem);
+            self.focused_mutate_down(problem, rng);
         }
     }
 
-    fn rand_mutate_down(&mut self) {
+    fn rand_mutate_down<R: Rng>(&mut self, problem: &KnapSack, rng: &mut R) {
         let keys = self.active_genes().collect::<Vec<_>>();
         if !keys.is_empty() {
-            let key = rand_mutation_key(keys);
-            self.mutate(key, false);
+            let key = rand_mutation_key(keys, rng);
+            self.set_gene(problem, key, false);
         }
     }
 
-    fn rand_mutate_up(&mut self) {
+    fn rand_mutate_up<R: Rng>(&mut self, problem: &KnapSack, rng: &mut R) {
         let keys = self.inactive_genes().collect::<Vec<_>>();
         if !keys.is_empty() {
-            let key = rand_mutation_key(keys);
-            self.mutate(key, false);
+            let key = rand_mutation_key(keys, rng);
+            self.set_gene(problem, key, false);
         }
     }
 
-    fn focused_mutate_up(&mut self, problem: &KnapSack) {
-        let keys = self.inactive_genes().collect::<Vec<_>>();
-        let req = problem.remains(self);
+    fn focused_mutate_up<R: Rng>(&mut self, problem: &KnapSack, rng: &mut R) {
+        let remains = self.remains(problem).collect::<Vec<_>>();
 
-        let mut possible_opts = Vec::new();
-        for k in keys {
-            let constraint = &problem.constraints[k];
-            if req
-                .iter()
-                .enumerate()
-                .all(|(index, (_, r))| r.amount > constraint[index] as i64)
-            {
-                possible_opts.push(k);
-            }
-        }
+        let possible_opts = self
+            .inactive_genes()
+            .filter(|g| {
+                remains
+                    .iter()
+                    .zip(problem.gene_constraints(*g))
+                    .all(|(r, c)| *r > i64::from(*c))
+            })
+            .collect::<Vec<_>>();
 
         if !possible_opts.is_empty() {
-            let pick = rand::thread_rng().gen_range(0..possible_opts.len());
-            self.mutate(possible_opts[pick].to_string(), true);
+            let pick = rng.gen_range(0..possible_opts.len());
+            self.set_gene(problem, possible_opts[pick], true);
         }
     }
 
-    fn focused_mutate_down(&mut self, problem: &KnapSack) {
-        let keys = self.active_genes().collect::<Vec<_>>();
-        let req = problem.remains(self);
+    fn focused_mutate_down<R: Rng>(&mut self, problem: &KnapSack, rng: &mut R) {
+        let remains = self.remains(problem).collect::<Vec<_>>();
 
-        let mut possible_opts = Vec::new();
-        for k in keys {
-            let constraint = &problem.constraints[k];
-            if req
-                .iter()
-                .enumerate()
-                .all(|(index, (_, r))| r.amount + constraint[index] as i64 >= 0)
-            {
-                possible_opts.push(k);
-            }
+        let possible_opts = self
+            .active_genes()
+            .filter(|g| {
+                remains
+                    .iter()
+                    .zip(problem.gene_constraints(*g))
+                    .all(|(r, c)| r + i64::from(*c) >= 0)
+            })
+            .collect::<Vec<_>>();
+
+        if !possible_opts.is_empty() {
+            let pick = rng.gen_range(0..possible_opts.len());
+            self.set_gene(problem, possible_opts[pick], true);
         }
+    }
+
+    /// Adds a unit of a product the remaining resources can still cover.
+    fn increment_quantity<R: Rng>(&mut self, problem: &KnapSack, rng: &mut R) {
+        let remains = self.remains(problem).collect::<Vec<_>>();
+
+        let possible_opts = (0..self.quantities.len())
+            .filter(|p| self.quantities[*p] < problem.maxima[*p])
+            .filter(|p| {
+                remains
+                    .iter()
+                    .zip(problem.product_constraints(*p))
+                    .all(|(r, c)| *c == 0 || *r >= i64::from(*c))
+            })
+            .collect::<Vec<_>>();
 
         if !possible_opts.is_empty() {
-            let pick = rand::thread_rng().gen_range(0..possible_opts.len());
-            self.mutate(possible_opts[pick].to_string(), true);
+            let p = possible_opts[rng.gen_range(0..possible_opts.len())];
+            self.set_quantity(problem, p, self.quantities[p] + 1);
         }
     }
 
-    pub fn active_genes(&self) -> impl Iterator<Item = &str> {
-        self.genotype
+    /// Removes a unit of a product using an overdrawn resource, or of any
+    /// product when none is overdrawn.
+    fn decrement_quantity<R: Rng>(&mut self, problem: &KnapSack, rng: &mut R) {
+        let remains = self.remains(problem).collect::<Vec<_>>();
+        let made = (0..self.quantities.len())
+            .filter(|p| self.quantities[*p] > 0)
+            .collect::<Vec<_>>();
+
+        let overdrawing = made
             .iter()
-            .filter(|(_k, g)| **g)
-            .map(|(k, _)| k.as_str())
+            .copied()
+            .filter(|p| {
+                remains
+                    .iter()
+                    .zip(problem.product_constraints(*p))
+                    .any(|(r, c)| *c > 0 && *r < 0)
+            })
+            .collect::<Vec<_>>();
+        let possible_opts = match overdrawing.is_empty() {
+            true => made,
+            false => overdrawing,
+        };
+
+        if !possible_opts.is_empty() {
+            let p = possible_opts[rng.gen_range(0..possible_opts.len())];
+            self.set_quantity(problem, p, self.quantities[p] - 1);
+        }
     }
 
-    pub fn inactive_genes(&self) -> impl Iterator<Item = &str> {
-        self.genotype
-            .iter()
-            .filter(|(_k, g)| !**g)
-            .map(|(k, _)| k.as_str())
+    pub fn active_genes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.genotype.ones()
+    }
+
+    pub fn inactive_genes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.genotype.zeros()
     }
 
     // fn enforce_valid_smart(&mut self) {
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::config::Config;
+
+use super::{Budget, Checkpoint, GeneticAlgorithm, KnapSack, Progress, Solution, Solver};
+
+/// Which islands receive the emigrants of an island.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    /// The next island only
+    #[default]
+    Ring,
+    /// Every other island
+    Full,
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ring" => Ok(Self::Ring),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("Unknown topology '{}', expected ring or full", s)),
+        }
+    }
+}
+
+/// Several genetic algorithms, one per island profile, exchanging their best
+/// individuals every `migration_interval` generations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IslandModel {
+    pub islands: Vec<Config>,
+    pub topology: Topology,
+    pub migration_interval: u32,
+    /// Individuals each island sends to each of its neighbours
+    pub migration_size: usize,
+    pub frequency: u32,
+    pub warm_start: Vec<BTreeMap<String, u32>>,
+}
+
+impl IslandModel {
+    /// Island profiles are the profile with the overrides of each island,
+    /// seeded one after the other from the profile seed.
+    pub fn new(config: &Config) -> Self {
+        let islands = config
+            .islands
+            .iter()
+            .enumerate()
+            .map(|(i, island)| {
+                let mut c = island.apply(config);
+                c.seed = config.seed.map(|s| s.wrapping_add(i as u64));
+                c
+            })
+            .collect();
+
+        Self {
+            islands,
+            topology: config.topology,
+            migration_interval: config.migration_interval,
+            migration_size: config.migration_size,
+            frequency: config.frequency,
+            warm_start: Vec::new(),
+        }
+    }
+
+    fn neighbours(&self, island: usize) -> Vec<usize> {
+        let n = self.islands.len();
+        match self.topology {
+            Topology::Ring => vec![(island + 1) % n],
+            Topology::Full => (0..n).filter(|j| *j != island).collect(),
+        }
+    }
+
+    /// Replaces the worst individuals of each island with the best ones of
+    /// the islands sending to it, the champion being always kept.
+    fn migrate(&self, islands: &[(GeneticAlgorithm, KnapSack)], states: &mut [Checkpoint]) {
+        let emigrants = islands
+            .iter()
+            .zip(states.iter())
+            .map(|((_, knapsack), state)| {
+                state
+                    .population
+                    .iter()
+                    .take(self.migration_size)
+                    .map(|i| knapsack.decode(i))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        for (from, plans) in emigrants.iter().enumerate() {
+            for to in self.neighbours(from) {
+                let knapsack = &islands[to].1;
+                let state = &mut states[to];
+                let room = state.population.len() - 1;
+
+                for plan in plans.iter().take(room) {
+                    let migrant = knapsack.encode(plan);
+                    if migrant.fitness > state.champion.fitness {
+                        state.champion = migrant.clone();
+                        state.champion_generation = state.generation;
+                    }
+                    state.population.pop();
+                    state.population.push(migrant);
+                }
+                state
+                    .population
+                    .sort_by_key(|i| std::cmp::Reverse(i.fitness));
+            }
+        }
+    }
+}
+
+impl Solver for IslandModel {
+    fn solve(&mut self, knapsack: &KnapSack, budget: &Budget) -> Result<Solution, String> {
+        if self.islands.is_empty() {
+            return Err(String::from("No island configured"));
+        }
+
+        let start = Instant::now();
+        let islands = self
+            .islands
+            .iter()
+            .map(|config| {
+                let mut ga = GeneticAlgorithm::new(config);
+                ga.frequency = 0;
+                ga.checkpoint = None;
+                ga.warm_start = self.warm_start.clone();
+                let mut k = knapsack.clone();
+                k.configure(config);
+                (ga, k)
+            })
+            .collect::<Vec<_>>();
+        let mut states = islands
+            .iter()
+            .map(|(ga, k)| ga.start(k))
+            .collect::<Vec<_>>();
+
+        let mut generation = 0_u32;
+        let mut best = (0_usize, states[0].champion.fitness);
+        let mut best_generation = 0_u32;
+
+        loop {
+            for (i, state) in states.iter().enumerate() {
+                if state.champion.fitness > best.1 {
+                    best = (i, state.champion.fitness);
+                    best_generation = generation;
+                }
+            }
+            let progress = Progress {
+                iterations: u64::from(generation),
+                best: best.1,
+                last_improvement: u64::from(best_generation),
+            };
+            if budget.exhausted(&progress, start) {
+                break;
+            }
+
+            generation += 1;
+            for ((ga, k), state) in islands.iter().zip(states.iter_mut()) {
+                ga.step(k, state);
+            }
+            if self.migration_interval > 0 && generation.is_multiple_of(self.migration_interval) {
+                self.migrate(&islands, &mut states);
+            }
+
+            if self.frequency > 0 && generation.is_multiple_of(self.frequency) {
+                println!(
+                    "Gen #{}, island champions: {} - current champion: {}",
+                    generation,
+                    states
+                        .iter()
+                        .map(|s| s.champion.fitness.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    best.1
+                );
+            }
+        }
+
+        let (island, _) = best;
+        let champion = &states[island].champion;
+        Ok(Solution {
+            quantities: islands[island].1.decode(champion),
+            fitness: champion.fitness,
+            generation: best_generation,
+            iterations: u64::from(generation),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IslandConfig;
+    use crate::solver::{Encoding, Verification};
+
+    #[test]
+    fn islands_mix_encodings() {
+        let config = Config {
+            population_size: 20,
+            frequency: 0,
+            path: String::from("./problem3-low.dat"),
+            seed: Some(42),
+            islands: vec![
+                IslandConfig::default(),
+                IslandConfig {
+                    encoding: Some(Encoding::Integer),
+                    mutations_per_1k: Some(300),
+                    ..Default::default()
+                },
+            ],
+            migration_interval: 5,
+            ..Default::default()
+        };
+        let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+        let budget = Budget {
+            iterations: Some(50),
+            ..Default::default()
+        };
+
+        let first = IslandModel::new(&config).solve(&knapsack, &budget).unwrap();
+        let other = IslandModel::new(&config).solve(&knapsack, &budget).unwrap();
+
+        assert_eq!(first, other);
+        assert!(Verification::new(&knapsack, &first).is_valid());
+    }
+}
@@ -3,49 +3,139 @@ use std::{collections::BTreeMap, fmt, fs};
 
 use crate::config::Config;
 
-use super::parser::{Product, Resource};
-use super::Individual;
+use super::parser::{ParseError, Product, Resource};
+use super::simplex::{LinearProgram, LpSolution};
+use super::solution::{ProductPlan, ResourceUsage};
+use super::{Diagnostic, Encoding, Individual, Repair, Solution};
 
+/// A power of two of a product quantity, in the binary encoding.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gene {
+    /// `{product}_{power}`
+    pub id: String,
+    /// Index of the product, in `products` order
+    pub product: usize,
+    pub power: u32,
+    pub value: u32,
+}
+
+/// Products and resources are indexed in the order of their maps, the
+/// constraint matrices hold one row per gene or product and one column per
+/// resource.
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct KnapSack {
     pub products: BTreeMap<String, Product>,
     pub resources: BTreeMap<String, Resource>,
-    pub constraints: BTreeMap<String, Vec<u32>>,
-    pub fitness: BTreeMap<String, u32>,
-    mutation_ratio: u32,
+    /// Sorted by id
+    pub genes: Vec<Gene>,
+    pub constraints: Vec<u32>,
+    pub requirements: Vec<u32>,
+    pub values: Vec<u32>,
+    pub maxima: Vec<u32>,
+    /// Largest quantity the genes of each product can encode
+    pub representable: Vec<u32>,
+    pub capacity: Vec<i64>,
+    pub encoding: Encoding,
+    pub repair: Repair,
 }
 
 impl KnapSack {
-    pub fn load_custom_kp(config: &Config) -> Self {
+    pub fn load_custom_kp(config: &Config) -> Result<Self, Vec<ParseError>> {
+        let data = fs::read_to_string(&config.path).map_err(|e| {
+            vec![ParseError {
+                file: config.path.to_string(),
+                message: format!("unable to read the file: {}", e),
+                ..Default::default()
+            }]
+        })?;
+
+        let mut knapsack = Self::parse(&data, &config.path)?;
+        knapsack.configure(config);
+        knapsack.compute_constraints();
+        Ok(knapsack)
+    }
+
+    /// Takes the genetic algorithm encoding and repair of a profile.
+    pub fn configure(&mut self, config: &Config) {
+        self.encoding = config.encoding;
+        self.repair = config.repair;
+    }
+
+    /// Reads resources and products from the .dat format, collecting every
+    /// error of the file rather than stopping at the first one.
+    pub fn parse(data: &str, file: &str) -> Result<Self, Vec<ParseError>> {
         let mut knapsack: KnapSack = KnapSack::default();
-        let data: String = fs::read_to_string(&config.path).expect("Unable to read the file");
-        let lines = data.split('\n').collect::<Vec<&str>>();
+        let mut errors = Vec::new();
+        // resource ids used by requirements, checked once every line is read
+        let mut references = Vec::new();
 
-        for v in lines {
-            let arr = v.trim().split(':').collect::<Vec<&str>>();
+        for (index, v) in data.lines().enumerate() {
+            if v.trim().is_empty() {
+                continue;
+            }
+
+            // 1-based column of each ':' separated field
+            let mut columns = Vec::new();
+            let mut offset = 0;
+            for part in v.split(':') {
+                columns.push(offset + part.len() - part.trim_start().len() + 1);
+                offset += part.len() + 1;
+            }
+            let arr = v.split(':').collect::<Vec<&str>>();
+
+            let error = |field: usize, message: String| ParseError {
+                file: file.to_string(),
+                line: index + 1,
+                column: columns.get(field).copied().unwrap_or(v.len() + 1),
+                message,
+            };
 
             match arr[0].trim().to_lowercase().as_str() {
-                "resource" => {
-                    let r = Resource::new(&arr[1..]);
-                    knapsack.resources.insert(arr[1].trim().to_string(), r);
-                }
-                "product" => {
-                    let p = Product::new(&arr[1..]);
-                    knapsack.products.insert(arr[1].trim().to_string(), p);
-                }
-                _ => (),
+                "resource" => match Resource::new(&arr[1..]) {
+                    Ok(r) if knapsack.resources.contains_key(&r.id) => {
+                        errors.push(error(1, format!("duplicate resource id `{}`", r.id)));
+                    }
+                    Ok(r) => {
+                        knapsack.resources.insert(r.id.to_string(), r);
+                    }
+                    Err(e) => errors.push(error(e.field + 1, e.message)),
+                },
+                "product" => match Product::new(&arr[1..]) {
+                    Ok(p) if knapsack.products.contains_key(&p.id) => {
+                        errors.push(error(1, format!("duplicate product id `{}`", p.id)));
+                    }
+                    Ok(p) => {
+                        for (i, q) in p.requirements.iter().enumerate() {
+                            references.push((q.id.to_string(), error(i + 3, String::new())));
+                        }
+                        knapsack.products.insert(p.id.to_string(), p);
+                    }
+                    Err(e) => errors.push(error(e.field + 1, e.message)),
+                },
+                other => errors.push(error(0, format!("unknown line type `{}`", other))),
             }
         }
 
-        knapsack.mutation_ratio = config.mutations_per_1k;
-        knapsack.compute_constraints();
-        knapsack
+        for (id, mut e) in references {
+            if !knapsack.resources.contains_key(&id) {
+                e.message = format!("requirement `{}` references unknown resource", id);
+                errors.push(e);
+            }
+        }
+        errors.sort_by_key(|e| (e.line, e.column));
+
+        if errors.is_empty() {
+            Ok(knapsack)
+        } else {
+            Err(errors)
+        }
     }
 
     pub fn compute_constraints(&mut self) {
         // let mut constraints: Vec<Vec<u32>> = Vec::new();
+        self.genes.clear();
 
-        for p in self.products.values_mut() {
+        for (index, p) in self.products.values_mut().enumerate() {
             let possible_max = p
                 .requirements
                 .iter()
@@ -55,7 +145,9 @@ impl KnapSack {
                     Some(r) => r.amount / i64::from(q.amount),
                 })
                 .min()
-                .expect("Something went wrong with product max");
+                // no positive requirement, reported by `check`
+                .unwrap_or(0)
+                .max(0);
 
             p.max = possible_max as u32;
             // println!("Found max to be {possible_max}");
@@ -69,47 +161,221 @@ impl KnapSack {
 
             // println!("Adding constraint from 2^0 to 2^{}", iter_max + offset - 1);
             for c in 0..(iter_max + offset) {
-                let mut cx = Vec::new();
-                for kr in self.resources.keys() {
-                    let i = p.requirements.iter().find(|q| &q.id == kr);
-                    match i {
-                        Some(r) => cx.push(r.amount * 2_u32.pow(c)),
-                        None => cx.push(0),
-                    }
-                }
+                self.genes.push(Gene {
+                    id: format!("{}_{}", p.id, c),
+                    product: index,
+                    power: c,
+                    value: p.value * 2_u32.pow(c),
+                });
+            }
+        }
+        self.genes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let matrix = self.requirement_matrix();
+        let column = |p: usize| matrix.iter().map(move |row| row[p]);
+        self.requirements = (0..self.products.len()).flat_map(column).collect();
+        self.constraints = self
+            .genes
+            .iter()
+            .flat_map(|g| column(g.product).map(|a| a * 2_u32.pow(g.power)))
+            .collect();
+        self.values = self.products.values().map(|p| p.value).collect();
+        self.maxima = self.products.values().map(|p| p.max).collect();
+        let mut bits = vec![0_u32; self.products.len()];
+        self.genes.iter().for_each(|g| bits[g.product] += 1);
+        self.representable = bits
+            .into_iter()
+            .map(|n| match n {
+                0 => 0,
+                n => u32::MAX >> (32 - n.min(32)),
+            })
+            .collect();
+        self.capacity = self.resources.values().map(|r| r.amount).collect();
+    }
 
-                let key = format!("{}_{}", p.id, c);
-                self.constraints.insert(key.clone(), cx);
-                self.fitness.insert(key, p.value * 2_u32.pow(c));
+    /// Resource requirements of a gene.
+    pub fn gene_constraints(&self, gene: usize) -> &[u32] {
+        let n = self.resources.len();
+        &self.constraints[gene * n..(gene + 1) * n]
+    }
+
+    /// Resource requirements of one unit of a product.
+    pub fn product_constraints(&self, product: usize) -> &[u32] {
+        let n = self.resources.len();
+        &self.requirements[product * n..(product + 1) * n]
+    }
 
-                // let key = format!("{}_{}", p.id, c);
+    /// Genotype positions: genes in the binary encoding, products in the
+    /// integer one.
+    pub fn positions(&self) -> usize {
+        match self.encoding {
+            Encoding::Binary => self.genes.len(),
+            Encoding::Integer => self.products.len(),
+        }
+    }
+
+    /// Semantic checks of a loaded problem, errors first. Products that can't be
+    /// solved for get a `max` of 0 in `compute_constraints` rather than a panic,
+    /// this is where they are reported.
+    pub fn check(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for r in self.resources.values() {
+            if r.amount < 0 {
+                diagnostics.push(Diagnostic::error(format!(
+                    "resource `{}` has a negative amount ({})",
+                    r.id, r.amount
+                )));
+            }
+            if !self
+                .products
+                .values()
+                .any(|p| p.requirements.iter().any(|q| q.id == r.id && q.amount > 0))
+            {
+                diagnostics.push(Diagnostic::warning(format!(
+                    "resource `{}` isn't used by any product",
+                    r.id
+                )));
+            }
+        }
+
+        for p in self.products.values() {
+            if self.resources.contains_key(&p.id) {
+                diagnostics.push(Diagnostic::warning(format!(
+                    "product id `{}` is also a resource id",
+                    p.id
+                )));
+            }
+            for q in &p.requirements {
+                if !self.resources.contains_key(&q.id) {
+                    diagnostics.push(Diagnostic::error(format!(
+                        "product `{}` requires undefined resource `{}`",
+                        p.id, q.id
+                    )));
+                }
             }
+
+            if !p.requirements.iter().any(|q| q.amount > 0) {
+                diagnostics.push(Diagnostic::error(format!(
+                    "product `{}` has no positive requirement, its quantity is unbounded",
+                    p.id
+                )));
+            } else if p.max == 0 {
+                diagnostics.push(Diagnostic::warning(format!(
+                    "product `{}` is unreachable, resources can't cover a single unit",
+                    p.id
+                )));
+            }
+            if p.value == 0 {
+                diagnostics.push(Diagnostic::warning(format!(
+                    "product `{}` has a zero value",
+                    p.id
+                )));
+            }
+        }
+
+        if self.products.is_empty() {
+            diagnostics.push(Diagnostic::error(String::from("no product defined")));
+        }
+
+        diagnostics.sort_by_key(|d| std::cmp::Reverse(d.severity));
+        diagnostics
+    }
+
+    /// Amount of each resource needed by one unit of each product, one row per
+    /// resource and one column per product.
+    pub fn requirement_matrix(&self) -> Vec<Vec<u32>> {
+        self.resources
+            .keys()
+            .map(|k| {
+                self.products
+                    .values()
+                    .map(|p| {
+                        p.requirements
+                            .iter()
+                            .filter(|q| &q.id == k)
+                            .map(|q| q.amount)
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The problem with fractional product quantities, each capped by its
+    /// `max`: one row per resource, then one row per product.
+    pub fn linear_program(&self) -> LinearProgram {
+        let mut rows = self
+            .requirement_matrix()
+            .iter()
+            .map(|row| row.iter().map(|a| f64::from(*a)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut bounds = self
+            .resources
+            .values()
+            .map(|r| r.amount as f64)
+            .collect::<Vec<_>>();
+
+        for (j, p) in self.products.values().enumerate() {
+            let mut row = vec![0.0; self.products.len()];
+            row[j] = 1.0;
+            rows.push(row);
+            bounds.push(f64::from(p.max));
+        }
+
+        LinearProgram {
+            objective: self.products.values().map(|p| f64::from(p.value)).collect(),
+            rows,
+            bounds,
         }
     }
 
+    /// Continuous optimum of the problem, an upper bound on any plan's value.
+    pub fn relaxation(&self) -> Result<LpSolution, String> {
+        self.linear_program().maximize()
+    }
+
     pub fn get_fitness(&self, indiv: &Individual) -> u32 {
         let mut total = 0_u32;
 
         for g in indiv.active_genes() {
-            total += self.fitness[g];
+            total += self.genes[g].value;
+        }
+        for (q, v) in indiv.quantities.iter().zip(&self.values) {
+            total += q * v;
         }
 
         total
     }
 
-    fn validate(&self, indiv: &Individual) -> bool {
-        let req = self.remains(indiv);
+    /// Computes `usage` and `fitness` of an individual from scratch, for when
+    /// its genes were set directly.
+    pub fn evaluate(&self, indiv: &mut Individual) {
+        indiv.usage = vec![0; self.resources.len()];
 
-        req.iter().all(|(_k, v)| v.amount >= 0)
+        for g in indiv.active_genes().collect::<Vec<_>>() {
+            for (u, c) in indiv.usage.iter_mut().zip(self.gene_constraints(g)) {
+                *u += i64::from(*c);
+            }
+        }
+        for (p, q) in indiv.quantities.iter().enumerate() {
+            for (u, c) in indiv.usage.iter_mut().zip(self.product_constraints(p)) {
+                *u += i64::from(*c) * i64::from(*q);
+            }
+        }
+
+        indiv.fitness = self.get_fitness(indiv);
+    }
+
+    pub fn validate(&self, indiv: &Individual) -> bool {
+        indiv.usage.iter().zip(&self.capacity).all(|(u, c)| u <= c)
     }
 
     pub fn remains(&self, indiv: &Individual) -> BTreeMap<String, Resource> {
         let mut req = self.resources.clone();
 
-        for active_gene in indiv.active_genes() {
-            for (index, (_, resource)) in req.iter_mut().enumerate() {
-                resource.amount -= i64::from(self.constraints[active_gene][index]);
-            }
+        for ((_, resource), used) in req.iter_mut().zip(&indiv.usage) {
+            resource.amount -= used;
         }
 
         req
@@ -118,144 +384,264 @@ impl KnapSack {
     pub fn requires(&self, indiv: &Individual) -> BTreeMap<String, Resource> {
         let mut req = self.resources.clone();
 
-        for active_gene in indiv.active_genes() {
-            for (index, item) in self.resources.iter().enumerate() {
-                let local_requirement = i64::from(self.constraints[active_gene][index]);
-
-                req.entry(item.0.to_string())
-                    .and_modify(|r| r.amount += local_requirement)
-                    .or_insert(Resource {
-                        id: item.1.id.to_string(),
-                        title: item.1.title.to_string(),
-                        amount: local_requirement,
-                    });
-            }
+        for ((_, resource), used) in req.iter_mut().zip(&indiv.usage) {
+            resource.amount += used;
         }
 
         req
     }
 
-    pub fn make_valid(&self, mut indiv: Individual) -> Individual {
+    pub fn make_valid<R: Rng>(&self, mut indiv: Individual, rng: &mut R) -> Individual {
+        if self.repair == Repair::Greedy && !self.validate(&indiv) {
+            return self.greedy_repair(indiv);
+        }
         while !self.validate(&indiv) {
-            indiv.mutate_down(self);
+            indiv.mutate_down(self, rng);
         }
-        indiv.fitness = self.get_fitness(&indiv);
         indiv
     }
 
-    pub fn cross_genes(&self, first: &Individual, other: &Individual) -> Individual {
-        let keys = self.fitness.keys().collect::<Vec<_>>();
+    /// Cuts the products of worst value density first, each by just enough
+    /// to clear the overdrawn resources it uses, then refills the slack with
+    /// the best ones.
+    pub fn greedy_repair(&self, indiv: Individual) -> Individual {
+        let order = density_order(&self.densities());
+        let mut quantities = self.quantities(&indiv);
+        let mut remains = indiv.remains(self).collect::<Vec<_>>();
+
+        for p in order.iter().rev() {
+            if remains.iter().all(|r| *r >= 0) {
+                break;
+            }
+            let constraints = self.product_constraints(*p);
+            let cut = remains
+                .iter()
+                .zip(constraints)
+                .filter(|(r, c)| **r < 0 && **c > 0)
+                .map(|(r, c)| (-r as u64).div_ceil(u64::from(*c)) as i64)
+                .max()
+                .map_or(0, |k| k.min(i64::from(quantities[*p])));
+
+            quantities[*p] -= cut as u32;
+            for (r, c) in remains.iter_mut().zip(constraints) {
+                *r += cut * i64::from(*c);
+            }
+        }
+
+        self.greedy_fill(&mut quantities, &mut remains, &order);
+        self.from_quantities(&quantities)
+    }
 
-        let mut output = Individual::default();
+    /// Individual filled greedily from nothing, with the densities scaled by
+    /// a random factor within `1 ± noise` for some variety.
+    pub fn construct<R: Rng>(&self, noise: f64, rng: &mut R) -> Individual {
+        let densities = self
+            .densities()
+            .into_iter()
+            .map(|d| match noise > 0.0 {
+                true => d * rng.gen_range(1.0 - noise..=1.0 + noise),
+                false => d,
+            })
+            .collect::<Vec<_>>();
+        let mut quantities = vec![0; self.products.len()];
+        let mut remains = self.capacity.clone();
+
+        self.greedy_fill(&mut quantities, &mut remains, &density_order(&densities));
+        self.from_quantities(&quantities)
+    }
 
-        match rand::thread_rng().gen_range(1..=3) {
-            1 => self.cross_1(keys, &mut output, first, other),
-            2 => self.cross_2(keys, &mut output, first, other),
-            3 => self.cross_rand(&mut output, first, other),
-            _ => (),
-        }
+    /// Adds as many units of each product as the remaining resources allow,
+    /// in the given order.
+    fn greedy_fill(&self, quantities: &mut [u32], remains: &mut [i64], order: &[usize]) {
+        for p in order {
+            let constraints = self.product_constraints(*p);
+            let room = remains
+                .iter()
+                .zip(constraints)
+                .filter(|(_, c)| **c > 0)
+                .map(|(r, c)| (*r).max(0) / i64::from(*c))
+                .min()
+                .unwrap_or(i64::MAX);
+            let added = room.min(i64::from(self.maxima[*p].saturating_sub(quantities[*p])));
 
-        if rand::thread_rng().gen_ratio(self.mutation_ratio, 1000) {
-            for _ in 0..rand::thread_rng().gen_range(1..=self.fitness.len() / 2) {
-                    output.mutate_up(self);                
+            quantities[*p] += added as u32;
+            for (r, c) in remains.iter_mut().zip(constraints) {
+                *r -= added * i64::from(*c);
             }
         }
+    }
+
+    /// Value per unit of scarce resource of each product, each requirement
+    /// counting for its share of the resource amount.
+    pub fn densities(&self) -> Vec<f64> {
+        (0..self.products.len())
+            .map(|p| {
+                let weight = self
+                    .product_constraints(p)
+                    .iter()
+                    .zip(&self.capacity)
+                    .filter(|(_, c)| **c > 0)
+                    .map(|(a, c)| f64::from(*a) / *c as f64)
+                    .sum::<f64>();
+                match weight > 0.0 {
+                    true => f64::from(self.values[p]) / weight,
+                    false => f64::INFINITY,
+                }
+            })
+            .collect()
+    }
 
-        output = self.make_valid(output);
+    /// Rank of each product by decreasing density.
+    pub fn density_ranks(&self) -> Vec<usize> {
+        let mut ranks = vec![0; self.products.len()];
+        for (rank, p) in density_order(&self.densities()).into_iter().enumerate() {
+            ranks[p] = rank;
+        }
 
-        output
+        ranks
     }
 
-    fn cross_1(
-        &self,
-        keys: Vec<&String>,
-        output: &mut Individual,
-        first: &Individual,
-        other: &Individual,
-    ) {
-        let k1 = rand::thread_rng().gen_range(0..(self.fitness.len()));
+    /// Quantity of each product made by an individual, in `products` order.
+    pub fn quantities(&self, indiv: &Individual) -> Vec<u32> {
+        let mut quantities = vec![0_u32; self.products.len()];
 
-        for g in self.fitness.keys() {
-            if g < keys[k1] {
-                output
-                    .genotype
-                    .entry(g.to_string())
-                    .or_insert(first.genotype[g]);
-            } else {
-                output
-                    .genotype
-                    .entry(g.to_string())
-                    .or_insert(other.genotype[g]);
-            }
+        for g in indiv.active_genes() {
+            let gene = &self.genes[g];
+            quantities[gene.product] += 2_u32.pow(gene.power);
         }
+        for (x, q) in quantities.iter_mut().zip(&indiv.quantities) {
+            *x += q;
+        }
+
+        quantities
     }
 
-    fn cross_2(
-        &self,
-        keys: Vec<&String>,
-        output: &mut Individual,
-        first: &Individual,
-        other: &Individual,
-    ) {
-        let k1 = rand::thread_rng().gen_range(0..(self.fitness.len()));
-        let k2 = rand::thread_rng().gen_range(0..(self.fitness.len()));
-        for g in self.fitness.keys() {
-            // if random() {
-            if ((g < keys[k1]) & (g < keys[k2])) | ((g > keys[k1]) & (g > keys[k2])) {
-                output
-                    .genotype
-                    .entry(g.to_string())
-                    .or_insert(first.genotype[g]);
-            } else {
-                output
-                    .genotype
-                    .entry(g.to_string())
-                    .or_insert(other.genotype[g]);
+    /// Individual making the given quantities, in `products` order, capped to
+    /// what the encoding can represent.
+    pub fn from_quantities(&self, wanted: &[u32]) -> Individual {
+        let mut indiv = Individual::empty(self);
+
+        match self.encoding {
+            Encoding::Binary => {
+                for (g, gene) in self.genes.iter().enumerate() {
+                    let q = wanted[gene.product].min(self.representable[gene.product]);
+                    indiv.genotype.set(g, (q >> gene.power) & 1 == 1);
+                }
+            }
+            Encoding::Integer => {
+                for (p, q) in indiv.quantities.iter_mut().enumerate() {
+                    *q = wanted[p].min(self.maxima[p]);
+                }
             }
         }
+
+        self.evaluate(&mut indiv);
+        indiv
     }
 
-    fn cross_rand(&self, output: &mut Individual, first: &Individual, other: &Individual) {
-        for g in self.fitness.keys() {
-            if rand::random() {
-                output
-                    .genotype
-                    .entry(g.to_string())
-                    .or_insert(first.genotype[g]);
-            } else {
-                output
-                    .genotype
-                    .entry(g.to_string())
-                    .or_insert(other.genotype[g]);
+    /// Product quantities encoded by an individual.
+    pub fn decode(&self, indiv: &Individual) -> BTreeMap<String, u32> {
+        self.products
+            .keys()
+            .cloned()
+            .zip(self.quantities(indiv))
+            .collect()
+    }
+
+    /// Individual making the given quantities, the reverse of `decode`.
+    /// Quantities are capped to what the encoding can represent, and unknown
+    /// products are ignored.
+    pub fn encode(&self, quantities: &BTreeMap<String, u32>) -> Individual {
+        let wanted = self
+            .products
+            .keys()
+            .map(|k| quantities.get(k).copied().unwrap_or(0))
+            .collect::<Vec<_>>();
+
+        self.from_quantities(&wanted)
+    }
+
+    /// Resources left once every product is made in the given quantity.
+    pub fn plan_remains(&self, quantities: &BTreeMap<String, u32>) -> BTreeMap<String, Resource> {
+        let mut req = self.resources.clone();
+
+        for (k, q) in quantities {
+            if let Some(product) = self.products.get(k) {
+                for r in &product.requirements {
+                    req.entry(r.id.to_string()).and_modify(|res| {
+                        let used = i64::from(r.amount).saturating_mul(i64::from(*q));
+                        res.amount = res.amount.saturating_sub(used);
+                    });
+                }
             }
         }
+
+        req
     }
 
-    pub fn explain_solution(
-        &self,
-        champion: &Individual,
-        champion_generation: u32,
-        config: &Config,
-    ) {
-        println!(
-            "\n-------\nFound {}$ worth solution at gen {}, performing {:.2}%",
-            champion.fitness,
-            champion_generation,
-            100_f64 * champion.fitness as f64 / config.known_best as f64
-        );
+    /// Fills the per-product and per-resource details of a solution from its
+    /// quantities.
+    pub fn annotate(&self, solution: &mut Solution) {
+        solution.products = self
+            .products
+            .values()
+            .map(|p| {
+                let quantity = solution.quantities.get(&p.id).copied().unwrap_or(0);
+                let plan = ProductPlan {
+                    quantity,
+                    unit_value: p.value,
+                    value: quantity * p.value,
+                    max: p.max,
+                };
+                (p.id.to_string(), plan)
+            })
+            .collect();
+
+        solution.resources = self
+            .plan_remains(&solution.quantities)
+            .into_iter()
+            .map(|(k, r)| {
+                let amount = self.resources[&k].amount;
+                let usage = ResourceUsage {
+                    amount,
+                    used: amount - r.amount,
+                    remaining: r.amount,
+                };
+                (k, usage)
+            })
+            .collect();
+    }
+
+    pub fn explain_solution(&self, champion: &Solution, config: &Config) {
+        if config.known_best > 0 {
+            println!(
+                "\n-------\nFound {}$ worth solution at gen {}, performing {:.2}%",
+                champion.fitness,
+                champion.generation,
+                100_f64 * champion.fitness as f64 / config.known_best as f64
+            );
+        } else {
+            println!(
+                "\n-------\nFound {}$ worth solution at gen {}",
+                champion.fitness, champion.generation
+            );
+        }
+        match self.relaxation() {
+            Ok(lp) if lp.value > 0.0 => println!(
+                "LP relaxation bound {:.2}$, gap {:.2}%",
+                lp.value,
+                100_f64 * (lp.value - champion.fitness as f64) / lp.value
+            ),
+            Ok(lp) => println!("LP relaxation bound {:.2}$, gap n/a", lp.value),
+            Err(msg) => println!("LP relaxation failed: {}", msg),
+        }
         // println!("Best solution is {:?}", champion);
         let mut solution = self.products.clone();
 
-        for c in champion.active_genes() {
-            let p = parse_constraint(c);
-            match p {
-                Ok((key, pow)) => {
-                    solution
-                        .entry(key)
-                        .and_modify(|p| p.solution += 2_u32.pow(pow));
-                }
-                Err(msg) => println!("{}", msg),
-            }
+        for (k, q) in &champion.quantities {
+            solution
+                .entry(k.to_string())
+                .and_modify(|p| p.solution = *q);
         }
 
         println!("\nSolution\n-------");
@@ -271,11 +657,18 @@ impl KnapSack {
         }
 
         println!("\nRemains\n-------");
-        let req = self.remains(champion);
+        let req = self.plan_remains(&champion.quantities);
         req.iter().for_each(|(k, v)| println!("{}: {:?}", k, v));
     }
 }
 
+/// Products by decreasing density.
+fn density_order(densities: &[f64]) -> Vec<usize> {
+    let mut order = (0..densities.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| densities[*b].total_cmp(&densities[*a]));
+    order
+}
+
 impl fmt::Display for KnapSack {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut output = String::from("Products\n");
@@ -289,13 +682,13 @@ impl fmt::Display for KnapSack {
         }
 
         output += "Output matrix\n";
-        for c in self.fitness.keys() {
+        for (g, gene) in self.genes.iter().enumerate() {
             output = format!(
                 "{}{}: {:?} => {}\n",
                 output,
-                c,
-                self.constraints[c.as_str()],
-                self.fitness[c]
+                gene.id,
+                self.gene_constraints(g),
+                gene.value
             );
         }
 
@@ -303,13 +696,101 @@ impl fmt::Display for KnapSack {
     }
 }
 
-fn parse_constraint(constraint: &str) -> Result<(String, u32), String> {
-    let parts = constraint.split('_').collect::<Vec<_>>();
-    match parts[..] {
-        [prod, pow] => match pow.parse::<u32>() {
-            Ok(p) => Ok((prod.to_string(), p)),
-            Err(msg) => Err(msg.to_string()),
-        },
-        _ => Err(String::from("invalid constraint key")),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Severity;
+
+    #[test]
+    fn encode_reverts_decode() {
+        let config = Config {
+            path: String::from("./problem.dat"),
+            ..Default::default()
+        };
+        let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+        let quantities = BTreeMap::from([
+            (String::from("p0"), 13),
+            (String::from("p1"), 0),
+            (String::from("p2"), 1),
+            (String::from("p3"), 40),
+            (String::from("p4"), 2),
+            (String::from("p5"), 7),
+        ]);
+
+        let indiv = knapsack.encode(&quantities);
+
+        assert_eq!(knapsack.decode(&indiv), quantities);
+    }
+
+    #[test]
+    fn check_reports_each_case() {
+        let check = |data: &str| {
+            let mut knapsack = KnapSack::parse(data, "test.dat").unwrap();
+            knapsack.compute_constraints();
+            knapsack
+                .check()
+                .into_iter()
+                .map(|d| (d.severity, d.message))
+                .collect::<Vec<_>>()
+        };
+        let parse_errors = |data: &str| {
+            KnapSack::parse(data, "test.dat")
+                .unwrap_err()
+                .into_iter()
+                .map(|e| e.message)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            check("resource: r0: a: -5\nproduct: p0: 3: r0=1"),
+            vec![
+                (
+                    Severity::Error,
+                    String::from("resource `r0` has a negative amount (-5)")
+                ),
+                (
+                    Severity::Warning,
+                    String::from(
+                        "product `p0` is unreachable, resources can't cover a single unit"
+                    )
+                ),
+            ]
+        );
+        assert_eq!(
+            check("resource: r0: a: 10\nproduct: p0: 0: r0=1"),
+            vec![(
+                Severity::Warning,
+                String::from("product `p0` has a zero value")
+            )]
+        );
+        assert_eq!(
+            check("resource: r0: a: 10\nproduct: p0: 3: r0=1\nproduct: p1: 3: r0=0"),
+            vec![(
+                Severity::Error,
+                String::from("product `p1` has no positive requirement, its quantity is unbounded")
+            )]
+        );
+        assert_eq!(
+            check("resource: r0: a: 10\nproduct: p0: 3: r0=1\nproduct: p1: 3: r0=11"),
+            vec![(
+                Severity::Warning,
+                String::from("product `p1` is unreachable, resources can't cover a single unit")
+            )]
+        );
+        // genes know their product, ids are free to contain the '_' of gene ids
+        let data = "resource: r0: a: 10\nproduct: p: 3: r0=1\nproduct: p_1: 2: r0=1";
+        let mut knapsack = KnapSack::parse(data, "test.dat").unwrap();
+        knapsack.compute_constraints();
+        let plan = BTreeMap::from([(String::from("p"), 5), (String::from("p_1"), 3)]);
+        assert_eq!(knapsack.check(), vec![]);
+        assert_eq!(knapsack.decode(&knapsack.encode(&plan)), plan);
+        assert_eq!(
+            parse_errors("resource: r0: a: 10\nproduct: p0: 3: r0=1\nproduct: p0: 4: r0=2"),
+            vec![String::from("duplicate product id `p0`")]
+        );
+        assert_eq!(
+            parse_errors("resource: r0: a: 10\nproduct: p0: 3: r0=1: r1=2"),
+            vec![String::from("requirement `r1` references unknown resource")]
+        );
     }
 }
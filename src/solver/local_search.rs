@@ -0,0 +1,107 @@
+use super::{Individual, KnapSack};
+
+/// Hill climbing over product quantities: adds a unit of a product, or
+/// trades a unit of a product for a unit of a more valuable one, taking the
+/// best feasible move until none improves.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LocalSearch;
+
+/// A unit of `add`, in place of a unit of `remove` when set.
+#[derive(Debug, Clone, Copy)]
+struct Move {
+    remove: Option<usize>,
+    add: usize,
+    gain: u32,
+}
+
+impl LocalSearch {
+    /// Local optimum reached from a feasible individual.
+    pub fn improve(&self, knapsack: &KnapSack, indiv: &Individual) -> Individual {
+        let mut quantities = knapsack.quantities(indiv);
+        let mut remains = indiv.remains(knapsack).collect::<Vec<_>>();
+
+        while let Some(m) = best_move(knapsack, &quantities, &remains) {
+            if let Some(p) = m.remove {
+                quantities[p] -= 1;
+                for (r, c) in remains.iter_mut().zip(knapsack.product_constraints(p)) {
+                    *r += i64::from(*c);
+                }
+            }
+            quantities[m.add] += 1;
+            for (r, c) in remains.iter_mut().zip(knapsack.product_constraints(m.add)) {
+                *r -= i64::from(*c);
+            }
+        }
+
+        let improved = knapsack.from_quantities(&quantities);
+        match improved.fitness > indiv.fitness && knapsack.validate(&improved) {
+            true => improved,
+            false => indiv.clone(),
+        }
+    }
+}
+
+/// The feasible move of highest gain, if any gains.
+fn best_move(knapsack: &KnapSack, quantities: &[u32], remains: &[i64]) -> Option<Move> {
+    let n = quantities.len();
+    let fits = |add: usize, freed: Option<usize>| {
+        let freed = freed.map(|p| knapsack.product_constraints(p));
+        knapsack
+            .product_constraints(add)
+            .iter()
+            .enumerate()
+            .all(|(i, c)| remains[i] + freed.map_or(0, |f| i64::from(f[i])) >= i64::from(*c))
+    };
+    let mut best: Option<Move> = None;
+
+    for add in (0..n).filter(|p| quantities[*p] < knapsack.maxima[*p]) {
+        let value = knapsack.values[add];
+        if value > best.map_or(0, |b| b.gain) && fits(add, None) {
+            best = Some(Move {
+                remove: None,
+                add,
+                gain: value,
+            });
+        }
+
+        for remove in (0..n).filter(|p| *p != add && quantities[*p] > 0) {
+            let gain = value.saturating_sub(knapsack.values[remove]);
+            if gain > best.map_or(0, |b| b.gain) && fits(add, Some(remove)) {
+                best = Some(Move {
+                    remove: Some(remove),
+                    add,
+                    gain,
+                });
+            }
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::Encoding;
+
+    #[test]
+    fn improves_to_a_local_optimum() {
+        for encoding in [Encoding::Binary, Encoding::Integer] {
+            let config = Config {
+                path: String::from("./problem4.dat"),
+                encoding,
+                ..Default::default()
+            };
+            let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+            let start = knapsack.from_quantities(&vec![0; knapsack.products.len()]);
+            let improved = LocalSearch.improve(&knapsack, &start);
+            let quantities = knapsack.quantities(&improved);
+            let remains = improved.remains(&knapsack).collect::<Vec<_>>();
+
+            assert!(knapsack.validate(&improved));
+            assert!(improved.fitness > start.fitness);
+            assert!(best_move(&knapsack, &quantities, &remains).is_none());
+        }
+    }
+}
@@ -1,7 +1,53 @@
+mod algorithm;
+mod annealing;
+mod bitset;
+mod bnb;
+mod checkpoint;
+mod crossover;
+mod diagnostic;
+mod exact;
+mod generator;
+mod genetic;
+mod greedy;
 mod individual;
+mod island;
 mod knapsack;
+mod local_search;
+mod mutation;
+mod selection;
+mod sensitivity;
+mod solution;
+mod verify;
 
-pub use individual::Individual;
-pub use knapsack::KnapSack;
+pub use algorithm::{Algorithm, Budget, Combine, Progress, Solver};
+pub use annealing::{Cooling, SimulatedAnnealing};
+pub use bitset::Bitset;
+pub use bnb::BranchAndBound;
+pub use checkpoint::Checkpoint;
+pub use crossover::{
+    Arithmetic, Crossover, CrossoverKind, Crossovers, DensityGuided, KPoint, ProductAware,
+    TwoPoint, Uniform,
+};
+pub use diagnostic::{Diagnostic, Severity};
+pub use exact::DynamicProgramming;
+pub use generator::Generator;
+pub use genetic::GeneticAlgorithm;
+pub use greedy::{Greedy, Repair};
+pub use individual::{Encoding, Individual};
+pub use island::{IslandModel, Topology};
+pub use knapsack::{Gene, KnapSack};
+pub use local_search::LocalSearch;
+pub use mutation::{
+    Adaptation, BitFlip, Mutation, MutationKind, Mutations, Reset, Shift, Step, Swap, Up,
+};
+pub use parser::{FieldError, ParseError, Product, Requirement, Resource};
+pub use selection::{
+    LinearRank, Roulette, Selection, SelectionScheme, StochasticUniversal, Tournament, Truncation,
+};
+pub use sensitivity::Sensitivity;
+pub use simplex::{LinearProgram, LpSolution};
+pub use solution::{ProductPlan, ResourceUsage, Solution};
+pub use verify::Verification;
 
-mod parser;
\ No newline at end of file
+mod parser;
+mod simplex;
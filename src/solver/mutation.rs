@@ -0,0 +1,401 @@
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::config::Config;
+
+use super::selection::spin;
+use super::{Encoding, Individual, KnapSack};
+
+/// Share of successful mutations the 1/5 rule aims for
+const TARGET_SUCCESS: f64 = 0.2;
+/// Factor the 1/5 rule multiplies or divides the rate by
+const RATE_STEP: f64 = 1.2;
+
+/// Random change to an offspring.
+pub trait Mutation: Debug + Send + Sync {
+    /// Mutates in place, keeping `usage` and `fitness` up to date. The result
+    /// may be infeasible, it is repaired afterwards.
+    fn mutate(&self, knapsack: &KnapSack, indiv: &mut Individual, rng: &mut dyn RngCore);
+}
+
+/// Mutation operators available in `Config`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MutationKind {
+    Up,
+    Shift,
+    Reset,
+    BitFlip,
+    Swap,
+    Step,
+}
+
+impl FromStr for MutationKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up" => Ok(Self::Up),
+            "shift" => Ok(Self::Shift),
+            "reset" => Ok(Self::Reset),
+            "bit_flip" => Ok(Self::BitFlip),
+            "swap" => Ok(Self::Swap),
+            "step" => Ok(Self::Step),
+            _ => Err(format!(
+                "Unknown mutation '{}', expected up, shift, reset, bit_flip, swap or step",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MutationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Up => "up",
+            Self::Shift => "shift",
+            Self::Reset => "reset",
+            Self::BitFlip => "bit_flip",
+            Self::Swap => "swap",
+            Self::Step => "step",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl MutationKind {
+    pub fn build(&self) -> Arc<dyn Mutation> {
+        match self {
+            Self::Up => Arc::new(Up),
+            Self::Shift => Arc::new(Shift),
+            Self::Reset => Arc::new(Reset),
+            Self::BitFlip => Arc::new(BitFlip),
+            Self::Swap => Arc::new(Swap),
+            Self::Step => Arc::new(Step),
+        }
+    }
+}
+
+/// How the mutation rate changes along a run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Adaptation {
+    /// `mutations_per_1k` throughout
+    #[default]
+    None,
+    /// Up when more than a fifth of the mutated children beat their parents,
+    /// down otherwise
+    OneFifth,
+    /// Up as the share of distinct individuals falls below
+    /// `diversity_target`
+    Diversity,
+}
+
+impl FromStr for Adaptation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "one_fifth" => Ok(Self::OneFifth),
+            "diversity" => Ok(Self::Diversity),
+            _ => Err(format!(
+                "Unknown adaptation '{}', expected none, one_fifth or diversity",
+                s
+            )),
+        }
+    }
+}
+
+/// The mutation operators of a run with their weights, and the rate at
+/// which offspring are mutated.
+#[derive(Debug, Clone)]
+pub struct Mutations {
+    pub kinds: Vec<MutationKind>,
+    operators: Vec<Arc<dyn Mutation>>,
+    pub weights: Vec<f64>,
+    /// Initial rate, per thousand offspring
+    pub rate: f64,
+    pub adaptation: Adaptation,
+    pub diversity_target: f64,
+}
+
+impl Mutations {
+    /// Operators and weights of `mutation`, or the encoding defaults when
+    /// empty: `up` for the binary encoding, `shift` and `reset` for the
+    /// integer one.
+    pub fn new(config: &Config) -> Self {
+        let weights = match config.mutation.is_empty() {
+            false => config.mutation.clone(),
+            true => match config.encoding {
+                Encoding::Binary => BTreeMap::from([(MutationKind::Up, 1.0)]),
+                Encoding::Integer => {
+                    BTreeMap::from([(MutationKind::Shift, 1.0), (MutationKind::Reset, 1.0)])
+                }
+            },
+        };
+
+        Self {
+            kinds: weights.keys().copied().collect(),
+            operators: weights.keys().map(|k| k.build()).collect(),
+            weights: weights.values().map(|w| w.max(0.0)).collect(),
+            rate: f64::from(config.mutations_per_1k.min(1000)),
+            adaptation: config.mutation_adaptation,
+            diversity_target: config.diversity_target.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Mutates `indiv` with one of the operators for `rate` per thousand
+    /// calls, and tells whether it did.
+    pub fn mutate(
+        &self,
+        rate: f64,
+        knapsack: &KnapSack,
+        indiv: &mut Individual,
+        rng: &mut dyn RngCore,
+    ) -> bool {
+        if self.operators.is_empty() || !rng.gen_bool((rate / 1000.0).clamp(0.0, 1.0)) {
+            return false;
+        }
+
+        let operator = spin(&self.weights, rng);
+        self.operators[operator].mutate(knapsack, indiv, rng);
+        true
+    }
+
+    /// Rate for the next generation, given how many children were mutated
+    /// and how many of those beat both their parents.
+    pub fn adapt(&self, rate: f64, mutated: u32, successes: u32, pop: &[Individual]) -> f64 {
+        let rate = match self.adaptation {
+            Adaptation::None => return self.rate,
+            Adaptation::OneFifth if mutated == 0 => rate,
+            Adaptation::OneFifth => match f64::from(successes) / f64::from(mutated) {
+                s if s > TARGET_SUCCESS => rate * RATE_STEP,
+                s if s < TARGET_SUCCESS => rate / RATE_STEP,
+                _ => rate,
+            },
+            Adaptation::Diversity => {
+                let diversity = diversity(pop);
+                match diversity < self.diversity_target {
+                    true => self.rate * self.diversity_target / diversity.max(0.01),
+                    false => self.rate,
+                }
+            }
+        };
+
+        rate.clamp(1.0, 1000.0)
+    }
+}
+
+/// Share of distinct individuals in a population.
+pub fn diversity(pop: &[Individual]) -> f64 {
+    match pop.len() {
+        0 => 0.0,
+        n => pop.iter().collect::<HashSet<_>>().len() as f64 / n as f64,
+    }
+}
+
+/// Switches on a few genes fitting the remaining resources, mostly, or at
+/// random. Adds a unit of a fitting product in the integer encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Up;
+
+impl Mutation for Up {
+    fn mutate(&self, knapsack: &KnapSack, indiv: &mut Individual, mut rng: &mut dyn RngCore) {
+        for _ in 0..rng.gen_range(1..=(knapsack.positions() / 2).max(1)) {
+            indiv.mutate_up(knapsack, &mut rng);
+        }
+    }
+}
+
+/// Moves a few product quantities by up to an eighth of their maximum.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Shift;
+
+impl Mutation for Shift {
+    fn mutate(&self, knapsack: &KnapSack, indiv: &mut Individual, rng: &mut dyn RngCore) {
+        let n = knapsack.products.len();
+        for _ in 0..rng.gen_range(1..=(n / 2).max(1)) {
+            with_quantities(knapsack, indiv, |quantities| {
+                if n == 0 {
+                    return;
+                }
+                let p = rng.gen_range(0..n);
+                let max = knapsack.maxima[p];
+                let k = rng.gen_range(1..=(max / 8).max(1));
+                quantities[p] = match rng.gen() {
+                    true => (quantities[p] + k).min(max),
+                    false => quantities[p].saturating_sub(k),
+                };
+            });
+        }
+    }
+}
+
+/// Draws a few product quantities anew.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Reset;
+
+impl Mutation for Reset {
+    fn mutate(&self, knapsack: &KnapSack, indiv: &mut Individual, rng: &mut dyn RngCore) {
+        let n = knapsack.products.len();
+        for _ in 0..rng.gen_range(1..=(n / 2).max(1)) {
+            with_quantities(knapsack, indiv, |quantities| {
+                if n == 0 {
+                    return;
+                }
+                let p = rng.gen_range(0..n);
+                quantities[p] = rng.gen_range(0..=knapsack.maxima[p]);
+            });
+        }
+    }
+}
+
+/// Flips each gene with a probability of one over the number of genes. In
+/// the integer encoding, flips a random bit of each quantity with the same
+/// odds.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BitFlip;
+
+impl Mutation for BitFlip {
+    fn mutate(&self, knapsack: &KnapSack, indiv: &mut Individual, rng: &mut dyn RngCore) {
+        let n = knapsack.positions();
+        if n == 0 {
+            return;
+        }
+
+        for i in 0..n {
+            if !rng.gen_ratio(1, n as u32) {
+                continue;
+            }
+            match knapsack.encoding {
+                Encoding::Binary => {
+                    let value = !indiv.genotype.get(i);
+                    indiv.set_gene(knapsack, i, value);
+                }
+                Encoding::Integer => {
+                    let max = knapsack.maxima[i];
+                    let bits = u32::BITS - max.leading_zeros();
+                    if bits > 0 {
+                        let q = indiv.quantities[i] ^ (1 << rng.gen_range(0..bits));
+                        indiv.set_quantity(knapsack, i, q.min(max));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Moves part of a product quantity to another product.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Swap;
+
+impl Mutation for Swap {
+    fn mutate(&self, knapsack: &KnapSack, indiv: &mut Individual, rng: &mut dyn RngCore) {
+        with_quantities(knapsack, indiv, |quantities| {
+            let made = (0..quantities.len())
+                .filter(|p| quantities[*p] > 0)
+                .collect::<Vec<_>>();
+            if made.is_empty() || quantities.len() < 2 {
+                return;
+            }
+            let from = made[rng.gen_range(0..made.len())];
+            let mut to = rng.gen_range(0..quantities.len() - 1);
+            if to >= from {
+                to += 1;
+            }
+
+            let room = knapsack.maxima[to].saturating_sub(quantities[to]);
+            let moved = rng.gen_range(1..=quantities[from]).min(room);
+            quantities[from] -= moved;
+            quantities[to] += moved;
+        });
+    }
+}
+
+/// Adds or removes a unit of a random product.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Step;
+
+impl Mutation for Step {
+    fn mutate(&self, knapsack: &KnapSack, indiv: &mut Individual, rng: &mut dyn RngCore) {
+        with_quantities(knapsack, indiv, |quantities| {
+            if quantities.is_empty() {
+                return;
+            }
+            let p = rng.gen_range(0..quantities.len());
+            quantities[p] = match rng.gen() {
+                true => (quantities[p] + 1).min(knapsack.maxima[p]),
+                false => quantities[p].saturating_sub(1),
+            };
+        });
+    }
+}
+
+/// Applies a change to the product quantities of an individual, whatever its
+/// encoding.
+fn with_quantities(knapsack: &KnapSack, indiv: &mut Individual, change: impl FnOnce(&mut [u32])) {
+    match knapsack.encoding {
+        Encoding::Integer => {
+            let mut quantities = indiv.quantities.clone();
+            change(&mut quantities);
+            for (p, q) in quantities.into_iter().enumerate() {
+                if q != indiv.quantities[p] {
+                    indiv.set_quantity(knapsack, p, q);
+                }
+            }
+        }
+        Encoding::Binary => {
+            let mut quantities = knapsack.quantities(indiv);
+            change(&mut quantities);
+            *indiv = knapsack.from_quantities(&quantities);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_fifth_rule() {
+        let config = Config {
+            mutations_per_1k: 100,
+            mutation_adaptation: Adaptation::OneFifth,
+            ..Default::default()
+        };
+        let mutations = Mutations::new(&config);
+
+        assert!(mutations.adapt(100.0, 10, 5, &[]) > 100.0);
+        assert!(mutations.adapt(100.0, 10, 1, &[]) < 100.0);
+        assert_eq!(mutations.adapt(100.0, 0, 0, &[]), 100.0);
+        assert_eq!(mutations.adapt(1.0, 10, 0, &[]), 1.0);
+    }
+
+    #[test]
+    fn diversity_raises_the_rate() {
+        let config = Config {
+            mutations_per_1k: 100,
+            mutation_adaptation: Adaptation::Diversity,
+            diversity_target: 0.5,
+            ..Default::default()
+        };
+        let mutations = Mutations::new(&config);
+        let distinct = (0..10)
+            .map(|fitness| Individual {
+                fitness,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let clones = vec![Individual::default(); 10];
+
+        assert_eq!(diversity(&distinct), 1.0);
+        assert_eq!(diversity(&clones), 0.1);
+        assert_eq!(mutations.adapt(300.0, 0, 0, &distinct), 100.0);
+        assert_eq!(mutations.adapt(100.0, 0, 0, &clones), 500.0);
+    }
+}
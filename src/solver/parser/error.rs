@@ -0,0 +1,44 @@
+use std::fmt;
+
+/// A problem in one field of a line, counted after the line type.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldError {
+    pub field: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: usize, message: String) -> Self {
+        Self { field, message }
+    }
+}
+
+/// Non empty field at `index`, or a `message` error when there is none.
+pub fn field<'a>(input: &[&'a str], index: usize, message: &str) -> Result<&'a str, FieldError> {
+    match input.get(index).map(|f| f.trim()) {
+        Some(f) if !f.is_empty() => Ok(f),
+        _ => Err(FieldError::new(index, message.to_string())),
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(
+                f,
+                "{}:{}:{}: {}",
+                self.file, self.line, self.column, self.message
+            )
+        }
+    }
+}
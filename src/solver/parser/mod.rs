@@ -1,7 +1,41 @@
+mod error;
 mod product;
 mod requirement;
 mod resource;
 
+pub use error::{FieldError, ParseError};
 pub use product::Product;
 pub use requirement::Requirement;
 pub use resource::Resource;
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::KnapSack;
+
+    #[test]
+    fn collects_every_error() {
+        let data = "resource: r0: rose: 10
+resource: r1: tulipe
+product: p0: 5: r0=1: r9=2
+product: p0: 6: r0=1
+product: p1: x: r0=1
+product: p2: 4: r0=
+stock: r2: 3
+";
+
+        let errors = KnapSack::parse(data, "bad.dat").unwrap_err();
+        let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "bad.dat:2:21: missing amount",
+                "bad.dat:3:23: requirement `r9` references unknown resource",
+                "bad.dat:4:10: duplicate product id `p0`",
+                "bad.dat:5:14: invalid value `x`: invalid digit found in string",
+                "bad.dat:6:17: missing amount",
+                "bad.dat:7:1: unknown line type `stock`",
+            ]
+        );
+    }
+}
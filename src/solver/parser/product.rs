@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::error::{field, FieldError};
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Product {
     pub id: String,
@@ -10,17 +12,31 @@ pub struct Product {
 }
 
 impl Product {
-    pub fn new(input: &[&str]) -> Self {
-        Self {
-            id: input[0].trim().to_string(),
-            value: input[1].trim().parse::<u32>().expect("Not a valid data"),
+    pub fn new(input: &[&str]) -> Result<Self, FieldError> {
+        let id = field(input, 0, "missing product id")?;
+        let value = field(input, 1, "missing value")?;
+
+        let mut requirements: Vec<super::Requirement> = Vec::new();
+        for (i, r) in input.iter().enumerate().skip(2) {
+            let req = super::Requirement::new(r).map_err(|msg| FieldError::new(i, msg))?;
+            if requirements.iter().any(|q| q.id == req.id) {
+                return Err(FieldError::new(
+                    i,
+                    format!("duplicate requirement `{}`", req.id),
+                ));
+            }
+            requirements.push(req);
+        }
+
+        Ok(Self {
+            id: id.to_string(),
+            value: value
+                .parse::<u32>()
+                .map_err(|e| FieldError::new(1, format!("invalid value `{}`: {}", value, e)))?,
             max: 0,
             solution: 0,
-            requirements: input[2..]
-                .iter()
-                .map(|input| super::Requirement::new(input.trim()))
-                .collect::<Vec<super::Requirement>>(),
-        }
+            requirements,
+        })
     }
 }
 
@@ -36,7 +52,7 @@ impl fmt::Display for Product {
                 .rev()
                 .map(|r| r.to_string())
                 .reduce(|acc, r| r + ", " + &acc)
-                .unwrap()
+                .unwrap_or_default()
         )
     }
 }
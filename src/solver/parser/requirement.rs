@@ -7,12 +7,19 @@ pub struct Requirement {
 }
 
 impl Requirement {
-    pub fn new(input: &str) -> Self {
-        let req = input.split('=').collect::<Vec<&str>>();
+    pub fn new(input: &str) -> Result<Self, String> {
+        let req = input.split('=').map(|s| s.trim()).collect::<Vec<&str>>();
 
-        Self {
-            id: req[0].to_string(),
-            amount: req[1].parse::<u32>().expect("Unable to parse requirement amount"),
+        match req[..] {
+            [""] | ["", _] => Err(String::from("missing resource id")),
+            [_] | [_, ""] => Err(String::from("missing amount")),
+            [id, amount] => Ok(Self {
+                id: id.to_string(),
+                amount: amount
+                    .parse::<u32>()
+                    .map_err(|e| format!("invalid amount `{}`: {}", amount, e))?,
+            }),
+            _ => Err(format!("invalid requirement `{}`", input)),
         }
     }
 }
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::error::{field, FieldError};
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Resource {
     pub id: String,
@@ -8,12 +10,22 @@ pub struct Resource {
 }
 
 impl Resource {
-    pub fn new(input: &[&str]) -> Self {
-        Self {
-            id: input[0].trim().to_string(),
-            title: String::from(input[1].trim()),
-            amount: input[2].trim().parse::<i64>().expect("Not a valid data"),
+    pub fn new(input: &[&str]) -> Result<Self, FieldError> {
+        let id = field(input, 0, "missing resource id")?;
+        let title = field(input, 1, "missing resource title")?;
+        let amount = field(input, 2, "missing amount")?;
+
+        if input.len() > 3 {
+            return Err(FieldError::new(3, String::from("unexpected field")));
         }
+
+        Ok(Self {
+            id: id.to_string(),
+            title: String::from(title),
+            amount: amount
+                .parse::<i64>()
+                .map_err(|e| FieldError::new(2, format!("invalid amount `{}`: {}", amount, e)))?,
+        })
     }
 }
 
@@ -0,0 +1,254 @@
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::config::Config;
+
+use super::Individual;
+
+/// Picks the parents of a generation.
+pub trait Selection: Debug + Send + Sync {
+    /// Indices of `count` parents in `pop`, in random order, taken two by
+    /// two for each child. `pop` is sorted by decreasing fitness.
+    fn select(&self, pop: &[Individual], count: usize, rng: &mut dyn RngCore) -> Vec<usize>;
+}
+
+/// Selection schemes available in `Config`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionScheme {
+    #[default]
+    Roulette,
+    Tournament,
+    Rank,
+    Sus,
+    Truncation,
+}
+
+impl FromStr for SelectionScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "roulette" => Ok(Self::Roulette),
+            "tournament" => Ok(Self::Tournament),
+            "rank" => Ok(Self::Rank),
+            "sus" => Ok(Self::Sus),
+            "truncation" => Ok(Self::Truncation),
+            _ => Err(format!(
+                "Unknown selection '{}', expected roulette, tournament, rank, sus or truncation",
+                s
+            )),
+        }
+    }
+}
+
+impl SelectionScheme {
+    /// The scheme with its parameters from the profile.
+    pub fn build(&self, config: &Config) -> Arc<dyn Selection> {
+        match self {
+            Self::Roulette => Arc::new(Roulette),
+            Self::Tournament => Arc::new(Tournament {
+                size: config.tournament_size.max(1),
+            }),
+            Self::Rank => Arc::new(LinearRank {
+                pressure: config.rank_pressure.clamp(1.0, 2.0),
+            }),
+            Self::Sus => Arc::new(StochasticUniversal),
+            Self::Truncation => Arc::new(Truncation {
+                ratio: config.truncation_ratio.clamp(0.0, 1.0),
+            }),
+        }
+    }
+}
+
+/// Fitness-proportional pick over a fraction of the total fitness, 1/1 to
+/// 1/3 at random, of the population reordered by `distribute`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Roulette;
+
+impl Selection for Roulette {
+    fn select(&self, pop: &[Individual], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let order = distribute(pop.len());
+        let total_fitness = pop.iter().map(|p| p.fitness).sum::<u32>();
+
+        (0..count)
+            .map(|_| order[pick_individual(rng, total_fitness, pop, &order)])
+            .collect()
+    }
+}
+
+/// Best of `size` individuals drawn at random.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tournament {
+    pub size: usize,
+}
+
+impl Selection for Tournament {
+    fn select(&self, pop: &[Individual], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                (0..self.size)
+                    .map(|_| rng.gen_range(0..pop.len()))
+                    .max_by_key(|i| pop[*i].fitness)
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+}
+
+/// Probability decreasing linearly with the rank, the best individual being
+/// `pressure` times as likely as the average one.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LinearRank {
+    pub pressure: f64,
+}
+
+impl Selection for LinearRank {
+    fn select(&self, pop: &[Individual], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let n = pop.len();
+        let weights = (0..n)
+            .map(|i| match n {
+                1 => 1.0,
+                _ => self.pressure - (2.0 * self.pressure - 2.0) * i as f64 / (n - 1) as f64,
+            })
+            .collect::<Vec<_>>();
+
+        (0..count).map(|_| spin(&weights, rng)).collect()
+    }
+}
+
+/// Fitness-proportional, with evenly spaced pointers from a single spin.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StochasticUniversal;
+
+impl Selection for StochasticUniversal {
+    fn select(&self, pop: &[Individual], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let total = pop.iter().map(|p| f64::from(p.fitness)).sum::<f64>();
+        if total == 0.0 || count == 0 {
+            return (0..count).map(|_| rng.gen_range(0..pop.len())).collect();
+        }
+
+        let step = total / count as f64;
+        let mut pointer = rng.gen::<f64>() * step;
+        let mut cumulative = 0.0;
+        let mut parents = Vec::with_capacity(count);
+        for (i, p) in pop.iter().enumerate() {
+            cumulative += f64::from(p.fitness);
+            while parents.len() < count && pointer < cumulative {
+                parents.push(i);
+                pointer += step;
+            }
+        }
+        while parents.len() < count {
+            parents.push(pop.len() - 1);
+        }
+
+        parents.shuffle(rng);
+        parents
+    }
+}
+
+/// Uniform pick among the best `ratio` of the population.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Truncation {
+    pub ratio: f64,
+}
+
+impl Selection for Truncation {
+    fn select(&self, pop: &[Individual], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let kept = ((pop.len() as f64 * self.ratio).ceil() as usize).clamp(1, pop.len());
+
+        (0..count).map(|_| rng.gen_range(0..kept)).collect()
+    }
+}
+
+/// Index drawn with probability proportional to its weight.
+pub(crate) fn spin(weights: &[f64], rng: &mut dyn RngCore) -> usize {
+    let total = weights.iter().sum::<f64>();
+    let mut pick = rng.gen::<f64>() * total;
+
+    for (i, w) in weights.iter().enumerate() {
+        if pick < *w {
+            return i;
+        }
+        pick -= w;
+    }
+
+    weights.len() - 1
+}
+
+/// Indices of the population alternately appended and prepended, so that
+/// the best individuals end up in the middle.
+fn distribute(len: usize) -> Vec<usize> {
+    let mut output = Vec::new();
+
+    for i in 0..len {
+        match i % 2 {
+            0 => output.push(i),
+            _ => output.insert(0, i),
+        }
+    }
+
+    output
+}
+
+fn pick_individual(
+    rng: &mut dyn RngCore,
+    total_fitness: u32,
+    pop: &[Individual],
+    order: &[usize],
+) -> usize {
+    if total_fitness == 0 {
+        return 0_usize;
+    }
+    let elite_ratio = rng.gen_range(1..4);
+    let pick_rng = rng.gen_range(0..total_fitness / elite_ratio);
+
+    let mut cur_fitness = pop[order[0]].fitness;
+    let mut cur_pick = 0;
+
+    while cur_fitness < pick_rng {
+        cur_pick += 1;
+        cur_fitness += pop[order[cur_pick]].fitness;
+    }
+
+    cur_pick
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn schemes_favour_the_fittest() {
+        let pop = (0..10)
+            .map(|i| Individual {
+                fitness: 100 - 10 * i,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let config = Config::default();
+
+        for scheme in [
+            SelectionScheme::Roulette,
+            SelectionScheme::Tournament,
+            SelectionScheme::Rank,
+            SelectionScheme::Sus,
+            SelectionScheme::Truncation,
+        ] {
+            let parents = scheme.build(&config).select(&pop, 1000, &mut rng);
+            let best = parents.iter().filter(|i| **i < 5).count();
+
+            assert_eq!(parents.len(), 1000);
+            assert!(parents.iter().all(|i| *i < pop.len()));
+            assert!(best > 500, "{:?} picked {} of the best half", scheme, best);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::{collections::BTreeMap, fmt};
+
+use super::KnapSack;
+
+const EPSILON: f64 = 1e-6;
+
+/// Dual information of the LP relaxation: what one more unit of each resource
+/// is worth, and how far each product is from paying for its resources.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sensitivity {
+    pub shadow_prices: BTreeMap<String, f64>,
+    pub remains: BTreeMap<String, f64>,
+    pub reduced_costs: BTreeMap<String, f64>,
+    pub quantities: BTreeMap<String, f64>,
+    titles: BTreeMap<String, String>,
+}
+
+impl Sensitivity {
+    pub fn new(knapsack: &KnapSack) -> Result<Self, String> {
+        let lp = knapsack.relaxation()?;
+        let matrix = knapsack.requirement_matrix();
+        let duals = &lp.duals[..knapsack.resources.len()];
+
+        let remains = knapsack
+            .resources
+            .values()
+            .zip(&matrix)
+            .map(|(r, row)| {
+                let used = row
+                    .iter()
+                    .zip(&lp.primal)
+                    .map(|(a, x)| f64::from(*a) * x)
+                    .sum::<f64>();
+                (r.id.to_string(), r.amount as f64 - used)
+            })
+            .collect();
+
+        // priced against resources only, so a product held back by its own
+        // `max` shows up with a positive reduced cost
+        let reduced_costs = knapsack
+            .products
+            .values()
+            .enumerate()
+            .map(|(j, p)| {
+                let cost = matrix
+                    .iter()
+                    .zip(duals)
+                    .map(|(row, y)| f64::from(row[j]) * y)
+                    .sum::<f64>();
+                (p.id.to_string(), f64::from(p.value) - cost)
+            })
+            .collect();
+
+        Ok(Self {
+            shadow_prices: knapsack
+                .resources
+                .keys()
+                .cloned()
+                .zip(duals.to_vec())
+                .collect(),
+            remains,
+            reduced_costs,
+            quantities: knapsack.products.keys().cloned().zip(lp.primal).collect(),
+            titles: knapsack
+                .resources
+                .values()
+                .map(|r| (r.id.to_string(), r.title.to_string()))
+                .collect(),
+        })
+    }
+
+    pub fn priced_out(&self) -> impl Iterator<Item = &str> {
+        self.reduced_costs
+            .iter()
+            .filter(|(_, c)| **c < -EPSILON)
+            .map(|(k, _)| k.as_str())
+    }
+}
+
+impl fmt::Display for Sensitivity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut output = String::from("Shadow prices\n-------\n");
+        for (k, price) in &self.shadow_prices {
+            output = format!(
+                "{}{} [{}]: {:+.2}$ per extra unit ({:.2} left)\n",
+                output,
+                self.titles[k],
+                k,
+                zeroed(*price),
+                zeroed(self.remains[k])
+            );
+        }
+
+        output += "\nReduced costs\n-------\n";
+        for (k, cost) in &self.reduced_costs {
+            let status = if *cost < -EPSILON {
+                String::from("priced out")
+            } else if *cost > EPSILON {
+                format!("capped at max, {:.2} made", self.quantities[k])
+            } else {
+                format!("{:.2} made", self.quantities[k])
+            };
+            output = format!("{}{}: {:.2}$ ({})\n", output, k, zeroed(*cost), status);
+        }
+
+        let priced_out = self.priced_out().collect::<Vec<_>>();
+        if !priced_out.is_empty() {
+            output = format!("{}Priced out: {}\n", output, priced_out.join(", "));
+        }
+
+        write!(f, "{}", output)
+    }
+}
+
+/// Rounding noise and -0.0 as 0.0, so that nothing prints as "-0.00".
+fn zeroed(x: f64) -> f64 {
+    if x.abs() < EPSILON {
+        0.0
+    } else {
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn only_tight_resources_have_a_price() {
+        let config = Config {
+            path: String::from("./problem.dat"),
+            ..Default::default()
+        };
+        let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+        let report = Sensitivity::new(&knapsack).unwrap();
+        let lp = knapsack.relaxation().unwrap();
+
+        assert!(report.remains.values().any(|r| r.abs() < EPSILON));
+        for (id, price) in &report.shadow_prices {
+            match report.remains[id].abs() < EPSILON {
+                true => assert!(*price > EPSILON, "{} is tight but free", id),
+                false => assert!(price.abs() < EPSILON, "{} is slack but priced", id),
+            }
+        }
+        assert_eq!(
+            report.quantities.values().copied().collect::<Vec<_>>(),
+            lp.primal
+        );
+        assert!(!report.to_string().contains("-0.00"));
+    }
+}
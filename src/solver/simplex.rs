@@ -0,0 +1,119 @@
+const EPSILON: f64 = 1e-9;
+
+/// Maximises `objective . x` subject to `rows . x <= bounds` and `x >= 0`.
+///
+/// Bounds must be non negative, so the slack basis is a feasible start and no
+/// phase one is needed, which always holds for knapsack relaxations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinearProgram {
+    pub objective: Vec<f64>,
+    pub rows: Vec<Vec<f64>>,
+    pub bounds: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LpSolution {
+    pub value: f64,
+    pub primal: Vec<f64>,
+    pub duals: Vec<f64>,
+    pub reduced_costs: Vec<f64>,
+}
+
+impl LinearProgram {
+    pub fn maximize(&self) -> Result<LpSolution, String> {
+        let n = self.objective.len();
+        let m = self.rows.len();
+
+        if let Some(b) = self.bounds.iter().find(|b| **b < 0.0) {
+            return Err(format!("negative bound {}", b));
+        }
+
+        // one row per constraint, structural columns then slack columns then rhs
+        let mut tableau = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let mut line = row.clone();
+                line.resize(n + m + 1, 0.0);
+                line[n + i] = 1.0;
+                line[n + m] = self.bounds[i];
+                line
+            })
+            .collect::<Vec<_>>();
+        let mut basis = (n..n + m).collect::<Vec<_>>();
+        let mut costs = self.objective.clone();
+        costs.resize(n + m, 0.0);
+        let mut value = 0.0;
+
+        // Bland's rule: lowest entering column, lowest leaving basis index
+        while let Some(enter) = (0..n + m).find(|j| costs[*j] > EPSILON) {
+            let leave = (0..m)
+                .filter(|i| tableau[*i][enter] > EPSILON)
+                .min_by(|a, b| {
+                    let ra = tableau[*a][n + m] / tableau[*a][enter];
+                    let rb = tableau[*b][n + m] / tableau[*b][enter];
+                    ra.total_cmp(&rb).then(basis[*a].cmp(&basis[*b]))
+                })
+                .ok_or_else(|| String::from("unbounded linear program"))?;
+
+            let pivot = tableau[leave][enter];
+            tableau[leave].iter_mut().for_each(|v| *v /= pivot);
+            let pivot_row = tableau[leave].clone();
+
+            for (i, row) in tableau.iter_mut().enumerate() {
+                let factor = row[enter];
+                if i != leave && factor != 0.0 {
+                    row.iter_mut()
+                        .zip(&pivot_row)
+                        .for_each(|(v, p)| *v -= factor * p);
+                }
+            }
+
+            let factor = costs[enter];
+            costs
+                .iter_mut()
+                .zip(&pivot_row)
+                .for_each(|(c, p)| *c -= factor * p);
+            value += factor * pivot_row[n + m];
+            basis[leave] = enter;
+        }
+
+        let mut primal = vec![0.0; n];
+        for (i, b) in basis.iter().enumerate() {
+            if *b < n {
+                primal[*b] = tableau[i][n + m];
+            }
+        }
+
+        Ok(LpSolution {
+            value,
+            primal,
+            duals: costs[n..].iter().map(|c| -c).collect(),
+            reduced_costs: costs[..n].to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn textbook_program() {
+        // max 3x + 5y, x <= 4, 2y <= 12, 3x + 2y <= 18 => x = 2, y = 6
+        let lp = LinearProgram {
+            objective: vec![3.0, 5.0],
+            rows: vec![vec![1.0, 0.0], vec![0.0, 2.0], vec![3.0, 2.0]],
+            bounds: vec![4.0, 12.0, 18.0],
+        };
+
+        let solution = lp.maximize().unwrap();
+
+        assert!((solution.value - 36.0).abs() < 1e-6);
+        assert!((solution.primal[0] - 2.0).abs() < 1e-6);
+        assert!((solution.primal[1] - 6.0).abs() < 1e-6);
+        assert!((solution.duals[1] - 1.5).abs() < 1e-6);
+        assert!((solution.duals[2] - 1.0).abs() < 1e-6);
+    }
+}
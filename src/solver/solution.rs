@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::config::Config;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ProductPlan {
+    pub quantity: u32,
+    pub unit_value: u32,
+    pub value: u32,
+    pub max: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ResourceUsage {
+    pub amount: i64,
+    pub used: i64,
+    pub remaining: i64,
+}
+
+/// A production plan. Solvers fill `quantities` and `fitness`; the per-product
+/// and per-resource details come from `KnapSack::annotate`, and the run
+/// metadata from the caller.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Solution {
+    pub quantities: BTreeMap<String, u32>,
+    pub fitness: u32,
+    pub generation: u32,
+    pub bound: Option<u32>,
+    /// Generations or nodes the solver went through
+    pub iterations: u64,
+    pub products: BTreeMap<String, ProductPlan>,
+    pub resources: BTreeMap<String, ResourceUsage>,
+    /// Solver runtime, in seconds
+    pub runtime: f64,
+    pub seed: Option<u64>,
+    pub config: Option<Config>,
+}
+
+impl Solution {
+    /// Relative distance to the proven bound, when the solver gives one.
+    pub fn gap(&self) -> Option<f64> {
+        self.bound.map(|b| match b {
+            0 => 0.0,
+            _ => f64::from(b.saturating_sub(self.fitness)) / f64::from(b),
+        })
+    }
+
+    pub fn is_optimal(&self) -> bool {
+        self.bound.is_some_and(|b| b <= self.fitness)
+    }
+
+    pub fn csv_header() -> &'static str {
+        "profile,type,id,quantity,unit_value,value,max,amount,used,remaining"
+    }
+
+    /// One row per product, one per resource and a total row.
+    pub fn csv_rows(&self, profile: usize) -> Vec<String> {
+        let mut rows = self
+            .products
+            .iter()
+            .map(|(k, p)| {
+                format!(
+                    "{},product,{},{},{},{},{},,,",
+                    profile,
+                    csv_field(k),
+                    p.quantity,
+                    p.unit_value,
+                    p.value,
+                    p.max
+                )
+            })
+            .collect::<Vec<_>>();
+
+        rows.extend(self.resources.iter().map(|(k, r)| {
+            format!(
+                "{},resource,{},,,,,{},{},{}",
+                profile,
+                csv_field(k),
+                r.amount,
+                r.used,
+                r.remaining
+            )
+        }));
+        rows.push(format!("{},total,,,,{},,,,", profile, self.fitness));
+
+        rows
+    }
+}
+
+/// Quoted, with its quotes doubled, when it holds a separator, a quote or a
+/// line break.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{Budget, Greedy, KnapSack, Solver};
+
+    #[test]
+    fn json_output_round_trips() {
+        let config = Config {
+            path: String::from("./problem.dat"),
+            seed: Some(1),
+            ..Default::default()
+        };
+        let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+        let mut solution = Greedy.solve(&knapsack, &Budget::default()).unwrap();
+        solution.runtime = 0.25;
+        solution.seed = config.seed;
+        solution.config = Some(config.clone());
+        knapsack.annotate(&mut solution);
+
+        let json = serde_json::to_string_pretty(&vec![&solution]).unwrap();
+        let read = serde_json::from_str::<Vec<Solution>>(&json).unwrap();
+
+        assert_eq!(read, vec![solution]);
+    }
+
+    #[test]
+    fn csv_quotes_ids() {
+        let solution = Solution {
+            products: BTreeMap::from([(String::from("a,\"b\""), ProductPlan::default())]),
+            resources: BTreeMap::from([(String::from("r0"), ResourceUsage::default())]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            solution.csv_rows(0),
+            vec![
+                "0,product,\"a,\"\"b\"\"\",0,0,0,0,,,",
+                "0,resource,r0,,,,,0,0,0",
+                "0,total,,,,0,,,,"
+            ]
+        );
+    }
+}
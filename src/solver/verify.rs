@@ -0,0 +1,224 @@
+use serde::Deserialize;
+use std::{collections::BTreeMap, fmt, fs};
+
+use super::{KnapSack, ResourceUsage, Solution};
+
+/// Independent check of a production plan against the problem, from the
+/// product requirements alone.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Verification {
+    /// Wide enough for any plan, even one overflowing the fitness range
+    pub value: u64,
+    pub claimed: Option<u32>,
+    pub resources: BTreeMap<String, ResourceUsage>,
+    pub unknown_products: Vec<String>,
+    titles: BTreeMap<String, String>,
+}
+
+/// Shapes accepted for a solution file: the output of `solve --output`, a
+/// single solution, or a bare map of product quantities.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SolutionFile {
+    Many(Vec<Solution>),
+    Quantities(BTreeMap<String, u32>),
+    One(Box<Solution>),
+}
+
+impl Solution {
+    /// Reads the solutions of a JSON or YAML file, chosen by its extension.
+    pub fn load(path: &str) -> Result<Vec<Solution>, String> {
+        let data =
+            fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+
+        let file = if path.ends_with(".json") {
+            serde_json::from_str::<SolutionFile>(&data).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str::<SolutionFile>(&data).map_err(|e| e.to_string())
+        };
+
+        match file.map_err(|msg| format!("Invalid solution file {}: {}", path, msg))? {
+            SolutionFile::Many(solutions) => Ok(solutions),
+            SolutionFile::Quantities(quantities) => Ok(vec![Solution {
+                quantities,
+                ..Default::default()
+            }]),
+            SolutionFile::One(solution) => Ok(vec![*solution]),
+        }
+    }
+}
+
+impl Verification {
+    pub fn new(knapsack: &KnapSack, solution: &Solution) -> Self {
+        let remains = knapsack.plan_remains(&solution.quantities);
+
+        Self {
+            value: solution
+                .quantities
+                .iter()
+                .filter_map(|(k, q)| {
+                    knapsack
+                        .products
+                        .get(k)
+                        .map(|p| u64::from(p.value) * u64::from(*q))
+                })
+                .fold(0, u64::saturating_add),
+            // solutions without any fitness, like bare quantities, claim nothing
+            claimed: Some(solution.fitness).filter(|f| *f > 0 || !solution.products.is_empty()),
+            resources: knapsack
+                .resources
+                .values()
+                .map(|r| {
+                    let remaining = remains[&r.id].amount;
+                    let usage = ResourceUsage {
+                        amount: r.amount,
+                        used: r.amount.saturating_sub(remaining),
+                        remaining,
+                    };
+                    (r.id.to_string(), usage)
+                })
+                .collect(),
+            unknown_products: solution
+                .quantities
+                .iter()
+                .filter(|(k, q)| **q > 0 && !knapsack.products.contains_key(*k))
+                .map(|(k, _)| k.to_string())
+                .collect(),
+            titles: knapsack
+                .resources
+                .values()
+                .map(|r| (r.id.to_string(), r.title.to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn violations(&self) -> impl Iterator<Item = (&String, &ResourceUsage)> {
+        self.resources.iter().filter(|(_, r)| r.remaining < 0)
+    }
+
+    /// Whether the value is too large for a fitness.
+    pub fn overflows(&self) -> bool {
+        self.value > u64::from(u32::MAX)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.violations().count() == 0
+            && self.unknown_products.is_empty()
+            && !self.overflows()
+            && self.claimed.is_none_or(|c| u64::from(c) == self.value)
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut output = String::new();
+        for (k, r) in &self.resources {
+            let status = if r.remaining < 0 {
+                format!("VIOLATED by {}", -r.remaining)
+            } else {
+                format!("{} left", r.remaining)
+            };
+            output = format!(
+                "{}{} [{}]: used {} of {}, {}\n",
+                output, self.titles[k], k, r.used, r.amount, status
+            );
+        }
+
+        for p in &self.unknown_products {
+            output = format!("{}unknown product `{}`\n", output, p);
+        }
+
+        output = match self.claimed {
+            _ if self.overflows() => {
+                format!(
+                    "{}Value: {}$, overflowing the fitness range\n",
+                    output, self.value
+                )
+            }
+            Some(c) if u64::from(c) != self.value => {
+                format!("{}Value: {}$, but {}$ claimed\n", output, self.value, c)
+            }
+            _ => format!("{}Value: {}$\n", output, self.value),
+        };
+
+        let violations = self.violations().count();
+        if self.is_valid() {
+            output += "Valid";
+        } else if violations > 0 {
+            output = format!("{}Invalid: {} resource violation(s)", output, violations);
+        } else {
+            output += "Invalid";
+        }
+
+        write!(f, "{}", output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn verify(quantities: &[(&str, u32)], fitness: u32) -> Verification {
+        let config = Config {
+            path: String::from("./problem.dat"),
+            ..Default::default()
+        };
+        let knapsack = KnapSack::load_custom_kp(&config).unwrap();
+        let solution = Solution {
+            quantities: quantities
+                .iter()
+                .map(|(k, q)| (k.to_string(), *q))
+                .collect(),
+            fitness,
+            ..Default::default()
+        };
+
+        Verification::new(&knapsack, &solution)
+    }
+
+    fn usage(amount: i64, used: i64) -> ResourceUsage {
+        ResourceUsage {
+            amount,
+            used,
+            remaining: amount - used,
+        }
+    }
+
+    #[test]
+    fn reports_usage_and_violations() {
+        let feasible = verify(&[("p0", 10), ("p4", 5)], 570);
+        assert!(feasible.is_valid());
+        assert_eq!(feasible.value, 570);
+        assert_eq!(feasible.resources["r0"], usage(160, 30));
+        assert_eq!(feasible.resources["r1"], usage(160, 50));
+        assert_eq!(feasible.resources["r2"], usage(160, 0));
+        assert_eq!(feasible.resources["r4"], usage(240, 20));
+
+        let infeasible = verify(&[("p0", 60)], 2100);
+        assert!(!infeasible.is_valid());
+        assert_eq!(infeasible.resources["r1"], usage(160, 180));
+        assert_eq!(
+            infeasible.violations().map(|(k, _)| k).collect::<Vec<_>>(),
+            vec!["r1"]
+        );
+        assert!(infeasible.to_string().contains("VIOLATED by 20"));
+
+        let unknown = verify(&[("p0", 1), ("px", 1)], 35);
+        assert!(!unknown.is_valid());
+        assert_eq!(unknown.unknown_products, vec!["px"]);
+        assert_eq!(unknown.violations().count(), 0);
+
+        let misclaimed = verify(&[("p0", 1)], 40);
+        assert!(!misclaimed.is_valid());
+        assert_eq!((misclaimed.value, misclaimed.claimed), (35, Some(40)));
+        assert!(misclaimed
+            .to_string()
+            .contains("Value: 35$, but 40$ claimed"));
+
+        let overflowing = verify(&[("p0", 200_000_000)], 0);
+        assert!(!overflowing.is_valid());
+        assert_eq!(overflowing.value, 7_000_000_000);
+        assert_eq!(overflowing.resources["r0"], usage(160, 200_000_000));
+    }
+}